@@ -0,0 +1,39 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+/// Clipboard programs to try, in order, with the arguments they need.
+const CLIPBOARD_COMMANDS: &[&[&str]] = &[
+    &["pbcopy"],
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Copies `text` to the system clipboard via the first available helper
+/// (pbcopy, wl-copy, xclip, or xsel).
+pub fn copy_to_clipboard(text: &str) -> Result<()> {
+    for candidate in CLIPBOARD_COMMANDS {
+        let (program, args) = (candidate[0], &candidate[1..]);
+        let child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .spawn();
+        let Ok(mut child) = child else { continue };
+        child
+            .stdin
+            .take()
+            .context("Clipboard helper has no stdin")?
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Could not write to {program}"))?;
+        let status = child
+            .wait()
+            .with_context(|| format!("Could not wait for {program}"))?;
+        if status.success() {
+            return Ok(());
+        }
+        bail!("{program} exited with {status}");
+    }
+    bail!("No clipboard helper found (tried pbcopy, wl-copy, xclip, xsel)");
+}
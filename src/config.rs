@@ -0,0 +1,529 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::loader::DuplicatePolicy;
+
+/// The filter command used when the config doesn't specify one.
+pub const DEFAULT_FILTER_COMMAND: &str = "fzf --ansi --height=50% --reverse";
+
+/// The picker label used when the config doesn't specify one.
+pub const DEFAULT_LABEL_TEMPLATE: &str = "{description} {tags}";
+
+/// User-level configuration, loaded from `cmdy.toml` in the config directory.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct AppConfig {
+    /// The program (plus arguments) used to pick a command interactively.
+    /// The special value `"auto"` probes PATH for fzf, sk, then gum.
+    pub filter_command: String,
+    /// The editor used by `cmdy edit`. Takes precedence over `$EDITOR`;
+    /// may include arguments (e.g. `code --wait`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub editor: Option<String>,
+    /// Extra directories to scan for snippet files, in addition to the
+    /// default commands directory.
+    pub directories: Vec<PathBuf>,
+    /// Directories to skip while scanning, compared canonically; lets a
+    /// broad `directories` entry ignore a noisy subfolder.
+    pub exclude_directories: Vec<PathBuf>,
+    /// Whether to descend into subdirectories when scanning.
+    pub recursive: bool,
+    /// What to do when two snippets share a uniqueness key: `"error"`
+    /// (default), `"first"`, or `"last"`.
+    pub duplicate_policy: DuplicatePolicy,
+    /// When set, `cmdy check` flags any snippet tag outside this list.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_tags: Option<Vec<String>>,
+    /// How picker lines are rendered. Tokens: `{description}`, `{tags}`,
+    /// `{dir}` (the source file's parent directory name), and `{file}`
+    /// (the source file's name).
+    pub label_template: String,
+    /// The flag used to pass `--query` text to a filter program cmdy
+    /// doesn't recognize (e.g. `"--search"`). fzf, skim, peco, and gum are
+    /// handled automatically.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter_query_flag: Option<String>,
+    /// Include each snippet's command text in the fuzzy-match input, so
+    /// the picker can find commands you remember by what they do rather
+    /// than what you called them. With fzf the text rides in a hidden
+    /// column; other filters see it inline.
+    pub search_command_text: bool,
+    /// Truncate picker labels to the terminal width (`$COLUMNS`, default
+    /// 80) with an ellipsis. Selection stays exact: a hidden key column
+    /// keeps truncated labels distinguishable.
+    pub truncate_descriptions: bool,
+    /// Whether a custom filter command renders ANSI escapes. fzf is assumed
+    /// to (it gets colored input); anything else gets plain text unless
+    /// this is set.
+    pub filter_supports_ansi: bool,
+    /// When true, every command executed through cmdy is appended to your
+    /// shell history, as if you had typed it yourself.
+    pub overwrite_shell_command: bool,
+    /// Load a `.env` next to each snippet's source file into the command's
+    /// environment (per-snippet `env` still wins). Off by default.
+    pub load_dotenv: bool,
+    /// Remember the last `--query` between runs and pre-populate the
+    /// picker with it. Clear the saved query with `--forget`.
+    pub remember_query: bool,
+    /// How many directory levels recursive scanning may descend (1 means
+    /// only the directory itself). Unset means unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_depth: Option<usize>,
+    /// The most picker entries one `expand` macro may generate; past this
+    /// the snippet is an error instead of a flood.
+    pub expand_cap: usize,
+    /// Record every executed command (timestamp, command, exit status) in
+    /// cmdy's own `history.jsonl`, viewable with `cmdy log`. Off by
+    /// default.
+    pub log_executions: bool,
+    /// HTTP(S) URLs of shared snippet files, fetched and cached on startup
+    /// (builds with the `remotes` feature only). Merged after directories,
+    /// with the usual duplicate policy.
+    pub remotes: Vec<String>,
+    /// The tag that pins a snippet to the top of the picker regardless of
+    /// sort mode. A lightweight alternative to numeric `priority`.
+    pub favorite_tag: String,
+    /// Accept snippet files with fields this version of cmdy doesn't know,
+    /// warning instead of erroring. Useful when a config tree is shared
+    /// with a newer cmdy. Off by default: strict parsing catches typos.
+    pub allow_unknown_fields: bool,
+    /// Suppress the "Copied command to clipboard" acknowledgment from
+    /// `cmdy clip`, for scripted clipboard use. `--quiet` does the same
+    /// per invocation.
+    pub quiet_clip: bool,
+    /// Run every command in a login shell (`-l`), as if typed into a fresh
+    /// terminal. Costs shell startup time on each run; snippets can opt in
+    /// individually with their own `login_shell` instead.
+    pub login_shell: bool,
+    /// Always show the resolved command and ask before running anything,
+    /// regardless of per-snippet `confirm` settings.
+    pub confirm_all: bool,
+    /// A shell command run before each execution; `{description}` and
+    /// `{command}` are substituted. A non-zero exit vetoes the run.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pre_exec: Option<String>,
+    /// A shell command run after each execution; `{description}`,
+    /// `{command}`, and `{status}` are substituted. Hook failures warn
+    /// rather than abort.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_exec: Option<String>,
+    /// Sort each command's tags alphabetically in the picker, for a
+    /// consistent look across files. Off by default: authored order is
+    /// kept.
+    pub sort_tags: bool,
+    /// With fzf, pass `--select-1 --exit-0` so a query matching exactly
+    /// one command is picked without opening the picker. Other filter
+    /// programs have no equivalent flags, so this is a no-op for them.
+    pub auto_select_unique: bool,
+    /// What happens to a picked command when no subcommand is given:
+    /// `"run"` (default), `"clip"`, or `"edit"`. For users who mostly copy
+    /// commands rather than execute them.
+    pub default_action: DefaultAction,
+    /// How to read the filter's selection when the filter can't echo the
+    /// input line back verbatim: cmdy prepends an index column and parses
+    /// it out of the output, e.g. `filter_output = { delimiter = ":",
+    /// field = 0 }`. Unset means the selection is matched as-is.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub filter_output: Option<FilterOutput>,
+}
+
+/// What `cmdy` with no subcommand does with the picked command.
+/// Configured via `default_action` in cmdy.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DefaultAction {
+    #[default]
+    Run,
+    Clip,
+    Edit,
+}
+
+/// The delimiter-and-field scheme for `filter_output`: the selected line
+/// is split on `delimiter` and field number `field` (zero-based) holds
+/// the index cmdy prepended.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct FilterOutput {
+    pub delimiter: String,
+    pub field: usize,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            filter_command: DEFAULT_FILTER_COMMAND.to_string(),
+            editor: None,
+            directories: Vec::new(),
+            exclude_directories: Vec::new(),
+            recursive: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            allowed_tags: None,
+            label_template: DEFAULT_LABEL_TEMPLATE.to_string(),
+            filter_query_flag: None,
+            search_command_text: false,
+            truncate_descriptions: false,
+            filter_supports_ansi: false,
+            overwrite_shell_command: false,
+            load_dotenv: false,
+            remember_query: false,
+            max_depth: None,
+            expand_cap: 100,
+            log_executions: false,
+            remotes: Vec::new(),
+            favorite_tag: "favorite".to_string(),
+            allow_unknown_fields: false,
+            quiet_clip: false,
+            login_shell: false,
+            confirm_all: false,
+            pre_exec: None,
+            post_exec: None,
+            sort_tags: false,
+            auto_select_unique: false,
+            default_action: DefaultAction::default(),
+            filter_output: None,
+        }
+    }
+}
+
+/// Returns the root of cmdy's config tree: `$XDG_CONFIG_HOME/cmdy` (or
+/// `~/.config/cmdy`) on Unix, `%APPDATA%\cmdy` on Windows.
+pub fn get_config_dir() -> Result<PathBuf> {
+    #[cfg(windows)]
+    {
+        let appdata = env::var("APPDATA").context("APPDATA is not set")?;
+        Ok(PathBuf::from(appdata).join("cmdy"))
+    }
+    #[cfg(not(windows))]
+    {
+        if let Ok(xdg) = env::var("XDG_CONFIG_HOME") {
+            if !xdg.is_empty() {
+                return Ok(PathBuf::from(xdg).join("cmdy"));
+            }
+        }
+        let home = env::var("HOME").context("HOME is not set")?;
+        Ok(PathBuf::from(home).join(".config").join("cmdy"))
+    }
+}
+
+/// The active profile from `$CMDY_PROFILE` (or `--profile`, which sets
+/// it). Unset or empty means the default profile.
+fn profile() -> Option<String> {
+    env::var("CMDY_PROFILE").ok().filter(|name| !name.is_empty())
+}
+
+/// Path to the config file: `cmdy.toml` (or `<profile>.toml` when a
+/// profile is active), falling back to the `.json` then `.yaml` variant
+/// when only one of those exists. When none exist, the TOML path is
+/// returned so error messages and `doctor` name the preferred file.
+pub fn get_config_file_path() -> Result<PathBuf> {
+    let dir = get_config_dir()?;
+    let stem = profile().unwrap_or_else(|| "cmdy".to_string());
+    let candidates: Vec<PathBuf> = ["toml", "json", "yaml"]
+        .iter()
+        .map(|ext| dir.join(format!("{stem}.{ext}")))
+        .collect();
+    Ok(candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .unwrap_or_else(|| candidates[0].clone()))
+}
+
+/// Parses config file contents in the format its extension indicates:
+/// `.json` and `.yaml` deserialize the same `AppConfig` (unknown keys
+/// still rejected); anything else is TOML.
+fn parse_config(path: &Path, contents: &str) -> Result<AppConfig> {
+    let config = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(contents)?,
+        Some("yaml") | Some("yml") => serde_yaml::from_str(contents)?,
+        _ => toml::from_str(contents)?,
+    };
+    Ok(config)
+}
+
+/// The default directory scanned for command snippet files: `commands`,
+/// or `commands-<profile>` when a profile is active.
+pub fn get_commands_dir() -> Result<PathBuf> {
+    let name = match profile() {
+        Some(profile) => format!("commands-{profile}"),
+        None => "commands".to_string(),
+    };
+    Ok(get_config_dir()?.join(name))
+}
+
+/// Expands a leading `~` and `$VAR`/`${VAR}` references in a configured
+/// path, so entries like `~/work/cmdy` or `$PROJECTS/snippets` scan the
+/// directory the user meant. Unset variables are left as-is.
+pub fn expand_path(path: &Path) -> PathBuf {
+    let Some(raw) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    let mut expanded = String::with_capacity(raw.len());
+    let rest = if raw == "~" || raw.starts_with("~/") {
+        match env::var("HOME") {
+            Ok(home) => {
+                expanded.push_str(&home);
+                &raw[1..]
+            }
+            Err(_) => raw,
+        }
+    } else {
+        raw
+    };
+    let mut chars = rest.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '$' {
+            expanded.push(c);
+            continue;
+        }
+        let after = &rest[index + 1..];
+        let (name, consumed) = if let Some(stripped) = after.strip_prefix('{') {
+            match stripped.find('}') {
+                Some(end) => (&stripped[..end], end + 2),
+                None => ("", 0),
+            }
+        } else {
+            let end = after
+                .find(|ch: char| !ch.is_ascii_alphanumeric() && ch != '_')
+                .unwrap_or(after.len());
+            (&after[..end], end)
+        };
+        match env::var(name) {
+            Ok(value) if !name.is_empty() => {
+                expanded.push_str(&value);
+                for _ in 0..consumed {
+                    chars.next();
+                }
+            }
+            _ => expanded.push('$'),
+        }
+    }
+    PathBuf::from(expanded)
+}
+
+/// Loads the app config, falling back to defaults when the file is missing.
+/// A file that exists but fails to parse produces a warning rather than an
+/// error, so a config typo never locks you out of your commands.
+pub fn load_app_config() -> AppConfig {
+    let path = match get_config_file_path() {
+        Ok(path) => path,
+        Err(_) => return AppConfig::default(),
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return AppConfig::default(),
+    };
+    match parse_config(&path, &contents) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Warning: could not parse {}: {err}", path.display());
+            AppConfig::default()
+        }
+    }
+}
+
+/// Directory-local overrides, read from a `.cmdy-dir.toml` inside a
+/// directory passed with `--dir`. Only picker-related settings belong
+/// here; everything else stays global.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub struct DirConfig {
+    /// Overrides the global `filter_command` when this directory is
+    /// scanned via `--dir`.
+    pub filter_command: Option<String>,
+}
+
+/// Applies `.cmdy-dir.toml` overrides from the explicitly passed `--dir`
+/// directories: the first directory that sets `filter_command` wins.
+/// Like the app config, a file that fails to parse warns rather than
+/// errors.
+pub fn apply_dir_overrides(config: &mut AppConfig, dirs: &[PathBuf]) {
+    for dir in dirs {
+        let path = dir.join(crate::loader::DIR_CONFIG_FILE);
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        let overrides: DirConfig = match toml::from_str(&contents) {
+            Ok(overrides) => overrides,
+            Err(err) => {
+                eprintln!("Warning: could not parse {}: {err}", path.display());
+                continue;
+            }
+        };
+        if let Some(filter_command) = overrides.filter_command {
+            config.filter_command = filter_command;
+            return;
+        }
+    }
+}
+
+/// Like [`load_app_config`], but a file that fails to parse is an error
+/// instead of a warning-and-defaults. Used by `cmdy config check`, whose
+/// whole point is surfacing the typos that the lenient loader hides. A
+/// missing file is still just the defaults.
+pub fn load_app_config_strict() -> Result<AppConfig> {
+    let path = get_config_file_path()?;
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(_) => return Ok(AppConfig::default()),
+    };
+    parse_config(&path, &contents)
+        .with_context(|| format!("Could not parse {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_uses_fzf() {
+        let config = AppConfig::default();
+        assert_eq!(config.filter_command, DEFAULT_FILTER_COMMAND);
+        assert!(config.directories.is_empty());
+        assert!(!config.recursive);
+    }
+
+    #[test]
+    fn config_parses_from_toml() {
+        let config: AppConfig = toml::from_str(
+            r#"
+            filter_command = "gum filter"
+            directories = ["/tmp/snippets"]
+            recursive = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.filter_command, "gum filter");
+        assert_eq!(config.directories, vec![PathBuf::from("/tmp/snippets")]);
+        assert!(config.recursive);
+    }
+
+    #[test]
+    fn tilde_expands_to_home() {
+        let home = env::var("HOME").unwrap();
+        let expanded = expand_path(Path::new("~/work/cmdy"));
+        assert_eq!(expanded, PathBuf::from(format!("{home}/work/cmdy")));
+    }
+
+    #[test]
+    fn env_vars_expand_in_both_forms() {
+        let home = env::var("HOME").unwrap();
+        assert_eq!(
+            expand_path(Path::new("$HOME/snippets")),
+            PathBuf::from(format!("{home}/snippets"))
+        );
+        assert_eq!(
+            expand_path(Path::new("${HOME}/snippets")),
+            PathBuf::from(format!("{home}/snippets"))
+        );
+    }
+
+    #[test]
+    fn absolute_paths_are_unchanged() {
+        assert_eq!(
+            expand_path(Path::new("/usr/local/share")),
+            PathBuf::from("/usr/local/share")
+        );
+    }
+
+    #[test]
+    fn unset_vars_are_left_alone() {
+        assert_eq!(
+            expand_path(Path::new("$CMDY_NO_SUCH_VAR_12345/x")),
+            PathBuf::from("$CMDY_NO_SUCH_VAR_12345/x")
+        );
+    }
+
+    #[test]
+    fn duplicate_policy_parses_from_toml() {
+        let config: AppConfig =
+            toml::from_str("duplicate_policy = \"last\"").unwrap();
+        assert_eq!(config.duplicate_policy, DuplicatePolicy::Last);
+        assert_eq!(
+            AppConfig::default().duplicate_policy,
+            DuplicatePolicy::Error
+        );
+    }
+
+    #[test]
+    fn profile_suffixes_the_config_file_and_commands_dir() {
+        // One test covers set and unset so parallel tests never see a
+        // half-set CMDY_PROFILE. The suffix logic is identical on the
+        // Windows and Unix branches of get_config_dir.
+        env::set_var("CMDY_PROFILE", "work");
+        assert_eq!(
+            get_config_file_path().unwrap().file_name().unwrap(),
+            "work.toml"
+        );
+        assert_eq!(
+            get_commands_dir().unwrap().file_name().unwrap(),
+            "commands-work"
+        );
+        env::remove_var("CMDY_PROFILE");
+        assert_eq!(
+            get_config_file_path().unwrap().file_name().unwrap(),
+            "cmdy.toml"
+        );
+        assert_eq!(
+            get_commands_dir().unwrap().file_name().unwrap(),
+            "commands"
+        );
+    }
+
+    #[test]
+    fn json_and_yaml_configs_load_like_the_toml_equivalent() {
+        let toml_config = parse_config(
+            Path::new("cmdy.toml"),
+            "filter_command = \"gum filter\"\ndirectories = [\"/tmp/snippets\"]\n",
+        )
+        .unwrap();
+        let json_config = parse_config(
+            Path::new("cmdy.json"),
+            r#"{"filter_command": "gum filter", "directories": ["/tmp/snippets"]}"#,
+        )
+        .unwrap();
+        let yaml_config = parse_config(
+            Path::new("cmdy.yaml"),
+            "filter_command: gum filter\ndirectories:\n  - /tmp/snippets\n",
+        )
+        .unwrap();
+        for config in [&toml_config, &json_config, &yaml_config] {
+            assert_eq!(config.filter_command, "gum filter");
+            assert_eq!(config.directories, vec![PathBuf::from("/tmp/snippets")]);
+        }
+        // Unknown keys are rejected in every format.
+        assert!(parse_config(Path::new("cmdy.json"), r#"{"no_such_key": true}"#).is_err());
+    }
+
+    #[test]
+    fn dir_overrides_beat_the_global_filter_command() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join(".cmdy-dir.toml"),
+            "filter_command = \"gum filter\"\n",
+        )
+        .unwrap();
+        let plain = tempfile::tempdir().unwrap();
+
+        let mut config = AppConfig::default();
+        // A directory without the file leaves the global value alone.
+        apply_dir_overrides(&mut config, &[plain.path().to_path_buf()]);
+        assert_eq!(config.filter_command, DEFAULT_FILTER_COMMAND);
+        // The first directory that sets filter_command wins.
+        apply_dir_overrides(
+            &mut config,
+            &[plain.path().to_path_buf(), dir.path().to_path_buf()],
+        );
+        assert_eq!(config.filter_command, "gum filter");
+    }
+
+    #[test]
+    fn unknown_config_keys_are_rejected() {
+        let result: Result<AppConfig, _> = toml::from_str("no_such_key = true");
+        assert!(result.is_err());
+    }
+}
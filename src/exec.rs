@@ -0,0 +1,726 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::process::{Command, Stdio};
+
+use anyhow::{bail, Context, Result};
+
+use crate::config;
+use crate::loader::{CommandDef, ParamSpec};
+use crate::usage;
+
+/// The shell used to run commands: `$SHELL`, falling back to `sh`.
+pub fn shell_command() -> String {
+    env::var("SHELL").unwrap_or_else(|_| "sh".to_string())
+}
+
+/// Extracts the unique `{{name}}` placeholder tokens from a command string,
+/// in order of first appearance.
+pub fn placeholder_names(command: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut rest = command;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("}}") else { break };
+        let name = rest[..end].trim();
+        if !name.is_empty() && !names.iter().any(|n| n == name) {
+            names.push(name.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    names
+}
+
+/// Resolves `{{name}}` tokens without prompting: `vars` (from `--var`)
+/// override `defaults`, and anything still unknown is left as `{{name}}`.
+/// Used by dry runs, which must never block on stdin.
+pub fn substitute_known_placeholders(
+    command: &str,
+    defaults: &BTreeMap<String, String>,
+    vars: &BTreeMap<String, String>,
+) -> String {
+    let mut resolved = command.to_string();
+    for name in placeholder_names(command) {
+        if let Some(value) = vars.get(&name).or_else(|| defaults.get(&name)) {
+            resolved = resolved.replace(&format!("{{{{{name}}}}}"), value);
+        }
+    }
+    resolved
+}
+
+/// Replaces `{{name}}` tokens using `defaults`, prompting interactively for
+/// any placeholder without a default.
+pub fn substitute_placeholders(
+    command: &str,
+    defaults: &BTreeMap<String, String>,
+) -> Result<String> {
+    substitute_placeholders_with_params(command, defaults, &[])
+}
+
+/// Like [`substitute_placeholders`], but validates values against the
+/// snippet's declared `params`: a default (or `--var`) of the wrong type
+/// is an error, an interactive entry of the wrong type re-prompts, and an
+/// empty entry for a required parameter aborts.
+pub fn substitute_placeholders_with_params(
+    command: &str,
+    defaults: &BTreeMap<String, String>,
+    params: &[ParamSpec],
+) -> Result<String> {
+    let mut resolved = command.to_string();
+    for name in placeholder_names(command) {
+        let spec = params.iter().find(|spec| spec.name == name);
+        let value = match defaults.get(&name) {
+            Some(value) => {
+                if let Some(spec) = spec {
+                    if !spec.param_type.accepts(value) {
+                        bail!(
+                            "{value:?} is not a valid {} for {name}",
+                            spec.param_type.label()
+                        );
+                    }
+                }
+                value.clone()
+            }
+            None => prompt_for_param(&name, spec)?,
+        };
+        resolved = resolved.replace(&format!("{{{{{name}}}}}"), &value);
+    }
+    Ok(resolved)
+}
+
+/// Prompts for one parameter, looping until the entry satisfies the
+/// declared type. An empty entry aborts when the parameter is required,
+/// and is accepted as-is otherwise.
+fn prompt_for_param(name: &str, spec: Option<&ParamSpec>) -> Result<String> {
+    loop {
+        let value = prompt_for_value(name)?;
+        let Some(spec) = spec else {
+            return Ok(value);
+        };
+        if value.is_empty() {
+            if spec.required {
+                bail!("Parameter {name} is required");
+            }
+            return Ok(value);
+        }
+        if spec.param_type.accepts(&value) {
+            return Ok(value);
+        }
+        eprintln!(
+            "{value:?} is not a valid {}; try again",
+            spec.param_type.label()
+        );
+    }
+}
+
+fn prompt_for_value(name: &str) -> Result<String> {
+    eprint!("{name}: ");
+    io::stderr().flush().ok();
+    let mut value = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut value)
+        .context("Could not read placeholder value")?;
+    Ok(value.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Asks a y/N question on the terminal, returning whether the user accepted.
+pub fn confirm(message: &str) -> Result<bool> {
+    eprint!("{message} [y/N] ");
+    io::stderr().flush().ok();
+    let mut answer = String::new();
+    io::stdin()
+        .lock()
+        .read_line(&mut answer)
+        .context("Could not read confirmation")?;
+    Ok(answer.trim().eq_ignore_ascii_case("y"))
+}
+
+/// Spawns a raw command string through the user's shell, inheriting the
+/// terminal, and returns its exit status.
+pub fn run_shell(command: &str) -> Result<std::process::ExitStatus> {
+    let shell = shell_command();
+    Command::new(&shell)
+        .arg("-c")
+        .arg(command)
+        .status()
+        .with_context(|| format!("Could not run shell {shell:?}"))
+}
+
+/// What actually ran and how it exited, for callers that need to react to
+/// the status (history, hooks) before deciding whether to error.
+pub struct ExecOutcome {
+    pub command: String,
+    pub status: std::process::ExitStatus,
+}
+
+/// Parses `.env` contents: `KEY=VALUE` lines, with blank lines, `#`
+/// comments, an optional `export ` prefix, and simple quoting handled.
+pub fn parse_dotenv(contents: &str) -> BTreeMap<String, String> {
+    let mut vars = BTreeMap::new();
+    for line in contents.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            continue;
+        }
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .or_else(|| value.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+            .unwrap_or(value);
+        vars.insert(key.to_string(), value.to_string());
+    }
+    vars
+}
+
+/// The variables from the `.env` sitting next to `source_file`, if any.
+/// Missing or unreadable files are just an empty set.
+pub fn dotenv_vars(source_file: &std::path::Path) -> BTreeMap<String, String> {
+    let Some(dir) = source_file.parent() else {
+        return BTreeMap::new();
+    };
+    match fs::read_to_string(dir.join(".env")) {
+        Ok(contents) => parse_dotenv(&contents),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// The argument vector for invoking the shell: `-l -c <command>` when a
+/// login shell is wanted, plain `-c <command>` otherwise.
+fn shell_args(command: &str, login_shell: bool) -> Vec<String> {
+    let mut args = Vec::new();
+    if login_shell {
+        args.push("-l".to_string());
+    }
+    args.push("-c".to_string());
+    args.push(command.to_string());
+    args
+}
+
+/// Quotes a single argument for POSIX `sh`: wrapped in single quotes,
+/// with embedded single quotes spliced via `'\''`. Plain words that need
+/// no quoting are left bare to keep dry-run output readable.
+pub fn shell_quote(arg: &str) -> String {
+    let safe = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:@%+,".contains(c));
+    if safe {
+        return arg.to_string();
+    }
+    format!("'{}'", arg.replace('\'', "'\\''"))
+}
+
+/// Runs the command through the user's shell, applying placeholder
+/// substitution, per-snippet environment, working directory, and the
+/// `confirm` prompt (forced for everything when `force_confirm` is set;
+/// likewise a login shell when `force_login_shell` is set).
+/// Returns `None` when the user declines the prompt, which callers treat
+/// as a clean abort. A command that exits non-zero is still an `Ok`
+/// outcome; only failing to run it at all is an error. Snippets with
+/// `retries` get re-run until an attempt succeeds or the budget is spent.
+#[allow(clippy::too_many_arguments)]
+pub fn execute_command(
+    cmd_def: &CommandDef,
+    force_confirm: bool,
+    force_login_shell: bool,
+    vars: &BTreeMap<String, String>,
+    load_dotenv: bool,
+    log_executions: bool,
+) -> Result<Option<ExecOutcome>> {
+    if cmd_def.command.trim().is_empty() {
+        bail!(
+            "Command {:?} is empty (check {})",
+            cmd_def.key(),
+            cmd_def.source_file.display()
+        );
+    }
+    // --var values behave like stronger defaults: they fill placeholders
+    // without a prompt, but anything not covered still asks.
+    let mut defaults = cmd_def.defaults.clone();
+    defaults.extend(vars.iter().map(|(k, v)| (k.clone(), v.clone())));
+    let command =
+        substitute_placeholders_with_params(&cmd_def.command, &defaults, &cmd_def.params)?;
+    if (force_confirm || cmd_def.confirm.is_required())
+        && !confirm(&cmd_def.confirm.prompt(&command))?
+    {
+        eprintln!("Aborted");
+        return Ok(None);
+    }
+    let shell = shell_command();
+    let mut child = Command::new(&shell);
+    child.args(shell_args(
+        &command,
+        force_login_shell || cmd_def.login_shell,
+    ));
+    // .env goes first so the snippet's own `env` entries win conflicts.
+    if load_dotenv {
+        for (key, value) in dotenv_vars(&cmd_def.source_file) {
+            child.env(key, value);
+        }
+    }
+    for (key, value) in &cmd_def.env {
+        child.env(key, value);
+    }
+    if let Some(cwd) = &cmd_def.cwd {
+        child.current_dir(cwd);
+    }
+    let mut status = run_attempt(&mut child, &shell, cmd_def.log_file.as_deref())?;
+    // Extra attempts for flaky commands: re-run until one succeeds (per
+    // `success_codes`) or the retry budget is spent. The last status, good
+    // or bad, is what gets reported.
+    let total_attempts = u64::from(cmd_def.retries) + 1;
+    for attempt in 2..=total_attempts {
+        if cmd_def.succeeded(status) {
+            break;
+        }
+        if cmd_def.retry_delay_secs > 0 {
+            std::thread::sleep(std::time::Duration::from_secs(
+                cmd_def.retry_delay_secs,
+            ));
+        }
+        eprintln!("Failed with {status}; attempt {attempt} of {total_attempts}");
+        status = run_attempt(&mut child, &shell, cmd_def.log_file.as_deref())?;
+    }
+    usage::record_usage(&cmd_def.description);
+    usage::save_last_run(cmd_def.key());
+    if log_executions {
+        let timestamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        usage::append_history(&usage::HistoryRecord {
+            timestamp,
+            description: cmd_def.description.clone(),
+            command: command.clone(),
+            status: status.code(),
+        });
+    }
+    Ok(Some(ExecOutcome { command, status }))
+}
+
+/// One run of the prepared shell invocation, teed to the log file when
+/// the snippet has one. Separated out so the retry loop above can re-run
+/// the same child definition.
+fn run_attempt(
+    child: &mut Command,
+    shell: &str,
+    log_file: Option<&std::path::Path>,
+) -> Result<std::process::ExitStatus> {
+    match log_file {
+        Some(log_file) => run_with_log(child, shell, log_file),
+        None => child
+            .status()
+            .with_context(|| format!("Could not run shell {shell:?}")),
+    }
+}
+
+/// Runs the child with its stdout and stderr teed to `log_file` (append
+/// mode, parents created) while still streaming to the terminal.
+fn run_with_log(
+    child: &mut Command,
+    shell: &str,
+    log_file: &std::path::Path,
+) -> Result<std::process::ExitStatus> {
+    let log_path = config::expand_path(log_file);
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create {}", parent.display()))?;
+    }
+    let log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&log_path)
+        .with_context(|| format!("Could not open log file {}", log_path.display()))?;
+    let log_for_stderr = log
+        .try_clone()
+        .context("Could not clone log file handle")?;
+    let mut spawned = child
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run shell {shell:?}"))?;
+    let child_stdout = spawned.stdout.take().context("Child has no stdout")?;
+    let child_stderr = spawned.stderr.take().context("Child has no stderr")?;
+    let out_thread =
+        std::thread::spawn(move || tee(child_stdout, io::stdout(), log));
+    let err_thread =
+        std::thread::spawn(move || tee(child_stderr, io::stderr(), log_for_stderr));
+    let status = spawned.wait().context("Could not wait for command")?;
+    for thread in [out_thread, err_thread] {
+        if let Ok(Err(err)) = thread.join() {
+            eprintln!("Warning: could not write log output: {err}");
+        }
+    }
+    Ok(status)
+}
+
+/// Copies everything from `source` to both `terminal` and `log`.
+fn tee(
+    mut source: impl Read,
+    mut terminal: impl Write,
+    mut log: impl Write,
+) -> io::Result<()> {
+    let mut buffer = [0u8; 8192];
+    loop {
+        let read = source.read(&mut buffer)?;
+        if read == 0 {
+            return Ok(());
+        }
+        terminal.write_all(&buffer[..read])?;
+        terminal.flush()?;
+        log.write_all(&buffer[..read])?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `record_usage` resolves its state directory from `XDG_STATE_HOME`,
+    /// which is process-wide: tests that actually execute a command take
+    /// this lock and point the variable at a private tempdir, so `cargo
+    /// test` never writes the developer's real state and never races over
+    /// which directory the variable names.
+    static STATE_HOME: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn scoped_state_home() -> (std::sync::MutexGuard<'static, ()>, tempfile::TempDir) {
+        let guard = STATE_HOME
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let dir = tempfile::tempdir().unwrap();
+        env::set_var("XDG_STATE_HOME", dir.path());
+        (guard, dir)
+    }
+
+    #[test]
+    fn extracts_placeholder_names_in_order() {
+        let names = placeholder_names("ssh {{user}}@{{host}} -p {{port}} # {{host}}");
+        assert_eq!(names, vec!["user", "host", "port"]);
+    }
+
+    #[test]
+    fn substitutes_from_defaults() {
+        let mut defaults = BTreeMap::new();
+        defaults.insert("host".to_string(), "example.com".to_string());
+        let resolved = substitute_placeholders("ping {{host}}", &defaults).unwrap();
+        assert_eq!(resolved, "ping example.com");
+    }
+
+    #[test]
+    fn command_without_placeholders_is_unchanged() {
+        let resolved =
+            substitute_placeholders("echo plain", &BTreeMap::new()).unwrap();
+        assert_eq!(resolved, "echo plain");
+    }
+
+    #[test]
+    fn known_placeholders_resolve_without_prompting() {
+        let mut defaults = BTreeMap::new();
+        defaults.insert("port".to_string(), "22".to_string());
+        let mut vars = BTreeMap::new();
+        vars.insert("host".to_string(), "example.com".to_string());
+        vars.insert("port".to_string(), "2222".to_string());
+        let resolved = substitute_known_placeholders(
+            "ssh {{user}}@{{host}} -p {{port}}",
+            &defaults,
+            &vars,
+        );
+        // vars beat defaults; the unprovided {{user}} stays visible.
+        assert_eq!(resolved, "ssh {{user}}@example.com -p 2222");
+    }
+
+    #[test]
+    fn int_params_reject_non_numeric_values() {
+        use crate::loader::ParamType;
+        assert!(ParamType::Int.accepts("8080"));
+        assert!(ParamType::Int.accepts("-1"));
+        assert!(!ParamType::Int.accepts("fast"));
+        assert!(!ParamType::Int.accepts("80.80"));
+        let params = vec![ParamSpec {
+            name: "port".to_string(),
+            param_type: ParamType::Int,
+            required: true,
+        }];
+        let mut defaults = BTreeMap::new();
+        defaults.insert("port".to_string(), "abc".to_string());
+        let err = substitute_placeholders_with_params(
+            "curl :{{port}}",
+            &defaults,
+            &params,
+        )
+        .expect_err("a non-numeric int default should fail");
+        assert!(err.to_string().contains("not a valid integer"));
+        defaults.insert("port".to_string(), "8080".to_string());
+        let resolved = substitute_placeholders_with_params(
+            "curl :{{port}}",
+            &defaults,
+            &params,
+        )
+        .unwrap();
+        assert_eq!(resolved, "curl :8080");
+    }
+
+    #[test]
+    fn shell_quote_leaves_safe_words_and_wraps_the_rest() {
+        assert_eq!(shell_quote("--dry-run"), "--dry-run");
+        assert_eq!(shell_quote("two words"), "'two words'");
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn login_shell_adds_the_l_flag() {
+        assert_eq!(shell_args("true", false), vec!["-c", "true"]);
+        assert_eq!(shell_args("true", true), vec!["-l", "-c", "true"]);
+    }
+
+    #[test]
+    fn dotenv_parsing_handles_comments_quotes_and_export() {
+        let vars = parse_dotenv(
+            "# comment\nFOO=bar\nexport BAZ=\"quoted value\"\n\nBAD LINE\nEMPTY=\n",
+        );
+        assert_eq!(vars["FOO"], "bar");
+        assert_eq!(vars["BAZ"], "quoted value");
+        assert_eq!(vars["EMPTY"], "");
+        assert_eq!(vars.len(), 3);
+    }
+
+    #[test]
+    fn dotenv_next_to_the_snippet_reaches_the_command() {
+        let (_lock, _state_home) = scoped_state_home();
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".env"), "CMDY_DOTENV_PROBE=yes\n").unwrap();
+        let marker = dir.path().join("marker");
+        let def = CommandDef {
+            description: "dotenv".to_string(),
+            command: format!(
+                "test \"$CMDY_DOTENV_PROBE\" = yes && touch {}",
+                marker.display()
+            ),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: dir.path().join("snippets.toml"),
+            line: 1,
+        };
+        let outcome = execute_command(&def, false, false, &BTreeMap::new(), true, false)
+            .unwrap()
+            .unwrap();
+        assert!(outcome.status.success());
+        assert!(marker.exists());
+    }
+
+    #[test]
+    fn log_file_captures_command_output() {
+        let (_lock, _state_home) = scoped_state_home();
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("logs").join("run.log");
+        let def = CommandDef {
+            description: "logged".to_string(),
+            command: "echo logged-line".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: Some(log_path.clone()),
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        let outcome =
+            execute_command(&def, false, false, &BTreeMap::new(), false, false)
+                .unwrap()
+                .unwrap();
+        assert!(outcome.status.success());
+        let logged = fs::read_to_string(&log_path).unwrap();
+        assert!(logged.contains("logged-line"));
+    }
+
+    #[test]
+    fn success_codes_accept_a_nonzero_exit() {
+        let (_lock, _state_home) = scoped_state_home();
+        let def = CommandDef {
+            description: "grep-like".to_string(),
+            command: "exit 1".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0, 1],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        let outcome = execute_command(&def, false, false, &BTreeMap::new(), false, false)
+            .unwrap()
+            .unwrap();
+        assert!(def.succeeded(outcome.status));
+        // The same exit fails a command with the default set.
+        let strict = CommandDef {
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            ..def
+        };
+        assert!(!strict.succeeded(outcome.status));
+    }
+
+    #[test]
+    fn logged_runs_append_one_history_record() {
+        let (_lock, _state_home) = scoped_state_home();
+        let def = CommandDef {
+            description: "remembered".to_string(),
+            command: "true".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        execute_command(&def, false, false, &BTreeMap::new(), false, true)
+            .unwrap()
+            .unwrap();
+        let records = usage::load_history(10);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].description, "remembered");
+        assert_eq!(records[0].command, "true");
+        assert_eq!(records[0].status, Some(0));
+        assert!(records[0].timestamp > 0);
+    }
+
+    #[test]
+    fn retries_rerun_a_command_that_fails_once() {
+        let (_lock, _state_home) = scoped_state_home();
+        let dir = tempfile::tempdir().unwrap();
+        let marker = dir.path().join("already-ran");
+        let def = CommandDef {
+            description: "flaky".to_string(),
+            // Fails the first time, succeeds once the marker exists.
+            command: format!(
+                "test -e {marker} || {{ touch {marker}; exit 1; }}",
+                marker = marker.display()
+            ),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            retries: 2,
+            retry_delay_secs: 0,
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        let outcome = execute_command(&def, false, false, &BTreeMap::new(), false, false)
+            .unwrap()
+            .unwrap();
+        assert!(outcome.status.success());
+    }
+
+    #[test]
+    fn empty_command_fails_fast() {
+        let def = CommandDef {
+            description: "hollow".to_string(),
+            command: "   ".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        let err = execute_command(&def, false, false, &BTreeMap::new(), false, false)
+            .err()
+            .expect("an empty command should be an error");
+        assert!(err.to_string().contains("is empty"));
+    }
+}
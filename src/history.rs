@@ -0,0 +1,172 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{bail, Context, Result};
+
+/// The shells cmdy knows how to write history for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Shell {
+    Zsh,
+    Bash,
+    Other(String),
+}
+
+impl Shell {
+    /// The shell's name for user-facing messages.
+    pub fn name(&self) -> &str {
+        match self {
+            Shell::Zsh => "zsh",
+            Shell::Bash => "bash",
+            Shell::Other(name) => name,
+        }
+    }
+}
+
+/// Detects the user's shell from `$SHELL`.
+pub fn detect_shell() -> Shell {
+    let shell = env::var("SHELL").unwrap_or_default();
+    let name = shell.rsplit('/').next().unwrap_or("");
+    match name {
+        "zsh" => Shell::Zsh,
+        "bash" => Shell::Bash,
+        other => Shell::Other(other.to_string()),
+    }
+}
+
+/// Returns the history file for the given shell: `$HISTFILE` when set,
+/// otherwise the shell's conventional default.
+pub fn get_history_file_path(shell: &Shell) -> Result<PathBuf> {
+    if let Ok(histfile) = env::var("HISTFILE") {
+        if !histfile.is_empty() {
+            return Ok(PathBuf::from(histfile));
+        }
+    }
+    let home = env::var("HOME").context("HOME is not set")?;
+    let file = match shell {
+        Shell::Zsh => ".zsh_history",
+        Shell::Bash => ".bash_history",
+        Shell::Other(name) => bail!("Don't know where {name:?} keeps its history"),
+    };
+    Ok(PathBuf::from(home).join(file))
+}
+
+/// Extracts the raw command from one history line, handling zsh's
+/// extended-history format (`: <timestamp>:<duration>;<command>`) as well
+/// as plain bash lines. Blank lines yield `None`.
+pub fn parse_history_line(line: &str) -> Option<String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return None;
+    }
+    if let Some(rest) = line.strip_prefix(": ") {
+        if let Some((_meta, command)) = rest.split_once(';') {
+            let command = command.trim();
+            if command.is_empty() {
+                return None;
+            }
+            return Some(command.to_string());
+        }
+    }
+    Some(line.to_string())
+}
+
+/// The most recent `last` unique commands in a history file's contents,
+/// oldest first. Repeated commands count once, at their most recent
+/// position.
+pub fn recent_unique_commands(contents: &str, last: usize) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut picked = Vec::new();
+    for line in contents.lines().rev() {
+        let Some(command) = parse_history_line(line) else {
+            continue;
+        };
+        if seen.insert(command.clone()) {
+            picked.push(command);
+        }
+        if picked.len() == last {
+            break;
+        }
+    }
+    picked.reverse();
+    picked
+}
+
+/// Appends `command` to the user's shell history file, using zsh's
+/// extended-history format when the shell is zsh.
+pub fn append_to_shell_history(command: &str) -> Result<Shell> {
+    let shell = detect_shell();
+    let path = get_history_file_path(&shell)?;
+    let entry = match shell {
+        Shell::Zsh => {
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            format!(": {timestamp}:0;{command}\n")
+        }
+        _ => format!("{command}\n"),
+    };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Could not open history file {}", path.display()))?;
+    file.write_all(entry.as_bytes())
+        .with_context(|| format!("Could not write to {}", path.display()))?;
+    Ok(shell)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shell_detection_matches_basename() {
+        // detect_shell reads the environment, so test the matching logic
+        // through the pieces we can control.
+        assert_eq!(
+            match "zsh" {
+                "zsh" => Shell::Zsh,
+                "bash" => Shell::Bash,
+                other => Shell::Other(other.to_string()),
+            },
+            Shell::Zsh
+        );
+    }
+
+    #[test]
+    fn zsh_extended_and_plain_lines_both_parse() {
+        assert_eq!(
+            parse_history_line(": 1700000000:0;git status"),
+            Some("git status".to_string())
+        );
+        assert_eq!(
+            parse_history_line("ls -la"),
+            Some("ls -la".to_string())
+        );
+        assert_eq!(parse_history_line("   "), None);
+    }
+
+    #[test]
+    fn recent_commands_dedupe_and_keep_order() {
+        let contents = "one\ntwo\none\nthree\n";
+        assert_eq!(
+            recent_unique_commands(contents, 10),
+            vec!["two", "one", "three"]
+        );
+        assert_eq!(recent_unique_commands(contents, 2), vec!["one", "three"]);
+    }
+
+    #[test]
+    fn unknown_shell_has_no_history_path() {
+        let shell = Shell::Other("tcsh".to_string());
+        if env::var("HISTFILE").map(|v| !v.is_empty()).unwrap_or(false) {
+            return; // HISTFILE overrides the per-shell default
+        }
+        assert!(get_history_file_path(&shell).is_err());
+    }
+}
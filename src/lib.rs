@@ -0,0 +1,25 @@
+//! Cmdy's snippet model, loading, and execution logic as a library.
+//!
+//! The `cmdy` binary is a thin CLI on top of these modules; other tools can
+//! depend on the crate to reuse the snippet format and loader.
+
+pub mod clipboard;
+pub mod config;
+pub mod exec;
+pub mod history;
+pub mod loader;
+#[cfg(feature = "remotes")]
+pub mod remotes;
+#[cfg(feature = "serve")]
+pub mod serve;
+#[cfg(feature = "tui")]
+pub mod tui;
+pub mod ui;
+pub mod usage;
+
+pub use config::{load_app_config, AppConfig};
+pub use exec::{execute_command, ExecOutcome};
+pub use loader::{
+    load_commands, CommandDef, CommandSnippet, Confirm, DuplicatePolicy, FileDef, FileMeta,
+    LoaderError,
+};
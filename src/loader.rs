@@ -0,0 +1,1617 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// What can go wrong while loading snippet files. Structured so library
+/// consumers can match on the failure kind; the binary converts to anyhow
+/// at its boundary with identical messages.
+#[derive(Debug, Error)]
+pub enum LoaderError {
+    #[error("{message}")]
+    Io {
+        message: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Could not parse {}", file.display())]
+    Parse {
+        file: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error(
+        "Duplicate command {kind} {name:?}\n  Defined in {}\n  Also defined in {}",
+        first.display(),
+        second.display()
+    )]
+    Duplicate {
+        kind: &'static str,
+        name: String,
+        first: PathBuf,
+        second: PathBuf,
+    },
+    #[error("Duplicate command {kind} {name:?} defined twice in {}", file.display())]
+    DuplicateInFile {
+        kind: &'static str,
+        name: String,
+        file: PathBuf,
+    },
+    #[error("Command {name:?} inherits from unknown base {base:?}")]
+    MissingBase { name: String, base: String },
+    #[error("Command {name:?} expands to {count} entries (cap is {cap})")]
+    ExpansionOverflow {
+        name: String,
+        count: usize,
+        cap: usize,
+    },
+    #[error("Base inheritance cycle involving {name:?}")]
+    BaseCycle { name: String },
+}
+
+/// A single command as written in a snippet file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandSnippet {
+    /// Optional: when omitted, derived from a leading `#` comment line in
+    /// the command, or from the command text itself.
+    pub description: Option<String>,
+    pub command: String,
+    /// Optional explicit uniqueness key. When present, descriptions may
+    /// repeat; the id must be unique instead.
+    pub id: Option<String>,
+    /// A glyph shown before the label in the picker (e.g. a Nerd Font
+    /// icon). Display only: it never takes part in fuzzy matching.
+    pub icon: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Ask for confirmation before running this command. Either a bool or
+    /// a custom prompt string.
+    #[serde(default)]
+    pub confirm: Confirm,
+    /// Extra environment variables for the command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Working directory to run the command in.
+    pub cwd: Option<PathBuf>,
+    /// Default values for `{{placeholder}}` tokens in the command.
+    #[serde(default)]
+    pub defaults: BTreeMap<String, String>,
+    /// Append the command's output to this file (in addition to the
+    /// terminal). `~` and environment variables are expanded.
+    pub log_file: Option<PathBuf>,
+    /// Run this command in a login shell (`-l`), so rc-file aliases and
+    /// PATH tweaks are available. Slower, since shell init runs each time.
+    #[serde(default)]
+    pub login_shell: bool,
+    /// Sort weight for the priority sort mode: higher floats to the top,
+    /// negative sinks below the default of 0.
+    #[serde(default)]
+    pub priority: i64,
+    /// Exit codes that count as success. Defaults to `[0]`; set it for
+    /// commands like grep or diff where non-zero is still a useful answer.
+    #[serde(default = "default_success_codes")]
+    pub success_codes: Vec<i32>,
+    /// Extra attempts after a failed run (judged by `success_codes`),
+    /// for flaky network-dependent commands. 0 (the default) runs once.
+    #[serde(default)]
+    pub retries: u32,
+    /// Seconds to wait between retry attempts.
+    #[serde(default)]
+    pub retry_delay_secs: u64,
+    /// Declared parameters for the command's `{{name}}` placeholders, with
+    /// optional type and required-ness: entered values are validated before
+    /// substitution.
+    #[serde(default)]
+    pub params: Vec<ParamSpec>,
+    /// The key (id or description) of another snippet to inherit `env`,
+    /// `tags`, `defaults`, and friends from; this snippet's own values win
+    /// conflicts. Resolved after everything is loaded.
+    pub base: Option<String>,
+    /// Macro variables, e.g. `expand = { region = ["us", "eu"] }`: the
+    /// snippet becomes one picker entry per combination, with `{{region}}`
+    /// substituted in the description and command.
+    #[serde(default)]
+    pub expand: BTreeMap<String, Vec<String>>,
+    /// Command variants to choose between at run time via a quick
+    /// secondary pick; the description stays a single picker entry.
+    #[serde(default)]
+    pub alternatives: Vec<String>,
+    /// Set to `false` to hide the snippet from the picker and run lookups
+    /// without deleting it; `cmdy list --all` still shows it.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+/// Snippets are enabled unless they opt out.
+fn default_enabled() -> bool {
+    true
+}
+
+/// One declared parameter:
+/// `params = [{ name = "port", type = "int", required = true }]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ParamSpec {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub param_type: ParamType,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// The value shapes a parameter can require. Strings accept anything, so
+/// they're the default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParamType {
+    #[default]
+    String,
+    Int,
+    Bool,
+}
+
+impl ParamType {
+    /// Whether `value` is acceptable for this type.
+    pub fn accepts(&self, value: &str) -> bool {
+        match self {
+            ParamType::String => true,
+            ParamType::Int => value.parse::<i64>().is_ok(),
+            ParamType::Bool => matches!(value, "true" | "false"),
+        }
+    }
+
+    /// The name used in prompts and error messages.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParamType::String => "string",
+            ParamType::Int => "integer",
+            ParamType::Bool => "boolean (true/false)",
+        }
+    }
+}
+
+/// The default for `success_codes`: only a zero exit counts.
+fn default_success_codes() -> Vec<i32> {
+    vec![0]
+}
+
+/// Whether (and how) to ask before running a command: `confirm = true`
+/// uses a generic prompt, while `confirm = "Really wipe the db?"` shows
+/// that exact message.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Confirm {
+    Enabled(bool),
+    Message(String),
+}
+
+impl Default for Confirm {
+    fn default() -> Self {
+        Confirm::Enabled(false)
+    }
+}
+
+impl Confirm {
+    /// Whether a prompt is required at all.
+    pub fn is_required(&self) -> bool {
+        match self {
+            Confirm::Enabled(enabled) => *enabled,
+            Confirm::Message(_) => true,
+        }
+    }
+
+    /// The prompt to show, given the command about to run.
+    pub fn prompt(&self, command: &str) -> String {
+        match self {
+            Confirm::Message(message) => message.clone(),
+            Confirm::Enabled(_) => format!("Run `{command}`?"),
+        }
+    }
+}
+
+/// What to do when two snippets share a uniqueness key: refuse to load
+/// (the default), keep the one seen first, or let the later one win.
+/// Configured via `duplicate_policy` in cmdy.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    #[default]
+    Error,
+    First,
+    Last,
+}
+
+/// The root of a snippet file: one or more `[[commands]]` tables, plus
+/// an optional `[meta]` header applying to the whole file.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileDef {
+    #[serde(default)]
+    pub meta: Option<FileMeta>,
+    pub commands: Vec<CommandSnippet>,
+}
+
+/// File-scoped metadata: a `[meta]` table at the top of a snippet file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FileMeta {
+    /// Tags unioned into every command in the file, for file-scoped
+    /// default tagging finer than a whole directory.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Lenient twin of [`FileDef`] without `deny_unknown_fields`, used when
+/// `allow_unknown_fields` is set so files written by a newer cmdy (with
+/// fields this version doesn't know) still load.
+#[derive(Debug, Deserialize)]
+struct LenientFileDef {
+    #[serde(default)]
+    meta: Option<FileMeta>,
+    commands: Vec<LenientCommandSnippet>,
+}
+
+/// Lenient twin of [`CommandSnippet`]; keep the fields in sync.
+#[derive(Debug, Deserialize)]
+struct LenientCommandSnippet {
+    description: Option<String>,
+    command: String,
+    id: Option<String>,
+    icon: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    confirm: Confirm,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+    cwd: Option<PathBuf>,
+    #[serde(default)]
+    defaults: BTreeMap<String, String>,
+    log_file: Option<PathBuf>,
+    #[serde(default)]
+    login_shell: bool,
+    #[serde(default)]
+    priority: i64,
+    #[serde(default = "default_success_codes")]
+    success_codes: Vec<i32>,
+    #[serde(default)]
+    retries: u32,
+    #[serde(default)]
+    retry_delay_secs: u64,
+    #[serde(default)]
+    params: Vec<ParamSpec>,
+    base: Option<String>,
+    #[serde(default)]
+    expand: BTreeMap<String, Vec<String>>,
+    #[serde(default)]
+    alternatives: Vec<String>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+impl From<LenientCommandSnippet> for CommandSnippet {
+    fn from(lenient: LenientCommandSnippet) -> Self {
+        CommandSnippet {
+            description: lenient.description,
+            command: lenient.command,
+            id: lenient.id,
+            icon: lenient.icon,
+            tags: lenient.tags,
+            confirm: lenient.confirm,
+            env: lenient.env,
+            cwd: lenient.cwd,
+            defaults: lenient.defaults,
+            log_file: lenient.log_file,
+            login_shell: lenient.login_shell,
+            priority: lenient.priority,
+            success_codes: lenient.success_codes,
+            retries: lenient.retries,
+            retry_delay_secs: lenient.retry_delay_secs,
+            params: lenient.params,
+            base: lenient.base,
+            expand: lenient.expand,
+            alternatives: lenient.alternatives,
+            enabled: lenient.enabled,
+        }
+    }
+}
+
+/// A command after loading, with the file it came from attached.
+#[derive(Debug, Clone)]
+pub struct CommandDef {
+    pub description: String,
+    pub command: String,
+    pub id: Option<String>,
+    pub icon: Option<String>,
+    pub tags: Vec<String>,
+    pub confirm: Confirm,
+    pub env: BTreeMap<String, String>,
+    pub cwd: Option<PathBuf>,
+    pub defaults: BTreeMap<String, String>,
+    pub log_file: Option<PathBuf>,
+    pub login_shell: bool,
+    pub priority: i64,
+    pub success_codes: Vec<i32>,
+    pub retries: u32,
+    pub retry_delay_secs: u64,
+    pub params: Vec<ParamSpec>,
+    pub base: Option<String>,
+    pub expand: BTreeMap<String, Vec<String>>,
+    pub alternatives: Vec<String>,
+    pub enabled: bool,
+    pub source_file: PathBuf,
+    /// 1-based line of the snippet's `[[commands]]` header in
+    /// `source_file`, for editors jumping to the definition. Snippets
+    /// whose header can't be located fall back to line 1.
+    pub line: usize,
+}
+
+impl CommandDef {
+    /// The uniqueness key: the explicit `id` when present, else the
+    /// description.
+    pub fn key(&self) -> &str {
+        self.id.as_deref().unwrap_or(&self.description)
+    }
+
+    /// Whether this exit status counts as success for this command,
+    /// honoring `success_codes`. A status with no code (killed by a
+    /// signal) is never a success.
+    pub fn succeeded(&self, status: std::process::ExitStatus) -> bool {
+        status
+            .code()
+            .is_some_and(|code| self.success_codes.contains(&code))
+    }
+}
+
+impl CommandSnippet {
+    /// The effective description: the explicit one when given, else the
+    /// first line of the command when it's a `#` comment (stripped), else
+    /// the command's first line itself, truncated to keep labels sane.
+    fn derived_description(&self) -> String {
+        if let Some(description) = &self.description {
+            return description.clone();
+        }
+        let first_line = self.command.lines().next().unwrap_or("").trim();
+        if let Some(comment) = first_line.strip_prefix('#') {
+            let comment = comment.trim();
+            if !comment.is_empty() {
+                return comment.to_string();
+            }
+        }
+        let mut description: String = first_line.chars().take(60).collect();
+        if first_line.chars().count() > 60 {
+            description.push('…');
+        }
+        description
+    }
+
+    fn into_def(self, source_file: PathBuf, line: usize) -> CommandDef {
+        CommandDef {
+            description: self.derived_description(),
+            command: self.command,
+            id: self.id,
+            icon: self.icon,
+            tags: self.tags,
+            confirm: self.confirm,
+            env: self.env,
+            cwd: self.cwd,
+            defaults: self.defaults,
+            log_file: self.log_file,
+            login_shell: self.login_shell,
+            priority: self.priority,
+            success_codes: self.success_codes,
+            retries: self.retries,
+            retry_delay_secs: self.retry_delay_secs,
+            params: self.params,
+            base: self.base,
+            expand: self.expand,
+            alternatives: self.alternatives,
+            enabled: self.enabled,
+            source_file,
+            line,
+        }
+    }
+}
+
+/// Expands macro snippets into one entry per combination of their
+/// `expand` variables, substituting `{{name}}` in both description and
+/// command. A description without the tokens gets a `(name=value)` suffix
+/// so the generated entries stay distinguishable. `cap` bounds the
+/// cartesian product, since a few long lists multiply quickly.
+pub fn expand_macros(
+    commands: &mut BTreeMap<String, CommandDef>,
+    cap: usize,
+) -> Result<(), LoaderError> {
+    let macro_keys: Vec<String> = commands
+        .iter()
+        .filter(|(_, def)| def.expand.values().any(|values| !values.is_empty()))
+        .map(|(key, _)| key.clone())
+        .collect();
+    for key in macro_keys {
+        let def = commands.remove(&key).expect("key was just listed");
+        let count: usize = def
+            .expand
+            .values()
+            .filter(|values| !values.is_empty())
+            .map(Vec::len)
+            .product();
+        if count > cap {
+            return Err(LoaderError::ExpansionOverflow {
+                name: key,
+                count,
+                cap,
+            });
+        }
+        for combo in cartesian(&def.expand) {
+            let mut expanded = def.clone();
+            expanded.expand = BTreeMap::new();
+            for (name, value) in &combo {
+                let token = format!("{{{{{name}}}}}");
+                expanded.description = expanded.description.replace(&token, value);
+                expanded.command = expanded.command.replace(&token, value);
+            }
+            if expanded.description == def.description {
+                let suffix: Vec<String> = combo
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect();
+                expanded.description =
+                    format!("{} ({})", def.description, suffix.join(", "));
+            }
+            if let Some(id) = &def.id {
+                let values: Vec<&str> =
+                    combo.iter().map(|(_, value)| *value).collect();
+                expanded.id = Some(format!("{id}-{}", values.join("-")));
+            }
+            commands.insert(expanded.key().to_string(), expanded);
+        }
+    }
+    Ok(())
+}
+
+/// The cartesian product of the expansion variables, as (name, value)
+/// assignments. Empty value lists are ignored rather than wiping out the
+/// whole product.
+fn cartesian(vars: &BTreeMap<String, Vec<String>>) -> Vec<Vec<(&str, &str)>> {
+    let mut combos: Vec<Vec<(&str, &str)>> = vec![Vec::new()];
+    for (name, values) in vars {
+        if values.is_empty() {
+            continue;
+        }
+        let mut next = Vec::new();
+        for combo in &combos {
+            for value in values {
+                let mut extended = combo.clone();
+                extended.push((name.as_str(), value.as_str()));
+                next.push(extended);
+            }
+        }
+        combos = next;
+    }
+    combos
+}
+
+/// Resolves `base` inheritance across a fully loaded set: each command
+/// walks its base chain and fills in anything it didn't set itself, with
+/// nearer ancestors winning over farther ones. An unknown base or a cycle
+/// is an error, since silently dropping inheritance would run commands
+/// with the wrong environment.
+pub fn resolve_bases(
+    commands: &mut BTreeMap<String, CommandDef>,
+) -> Result<(), LoaderError> {
+    let snapshot = commands.clone();
+    for (key, def) in commands.iter_mut() {
+        let mut seen = vec![key.clone()];
+        let mut next = def.base.clone();
+        while let Some(base_key) = next {
+            if seen.contains(&base_key) {
+                return Err(LoaderError::BaseCycle { name: key.clone() });
+            }
+            let Some(base) = snapshot.get(&base_key) else {
+                return Err(LoaderError::MissingBase {
+                    name: key.clone(),
+                    base: base_key,
+                });
+            };
+            inherit(def, base);
+            seen.push(base_key);
+            next = base.base.clone();
+        }
+    }
+    Ok(())
+}
+
+/// Copies inheritable fields from `base` into `def` without overriding
+/// anything `def` already set (map entries, tags, and params merge;
+/// scalars fill only when unset).
+fn inherit(def: &mut CommandDef, base: &CommandDef) {
+    for (key, value) in &base.env {
+        def.env.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    for (key, value) in &base.defaults {
+        def.defaults.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    for tag in &base.tags {
+        if !def.tags.contains(tag) {
+            def.tags.push(tag.clone());
+        }
+    }
+    for param in &base.params {
+        if !def.params.iter().any(|spec| spec.name == param.name) {
+            def.params.push(param.clone());
+        }
+    }
+    if def.cwd.is_none() {
+        def.cwd = base.cwd.clone();
+    }
+    if def.log_file.is_none() {
+        def.log_file = base.log_file.clone();
+    }
+    if base.login_shell {
+        def.login_shell = true;
+    }
+}
+
+/// Tallies from a directory scan, used to explain an empty result: a
+/// directory with no `.toml` files and one whose files all failed to
+/// parse deserve different messages.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LoadSummary {
+    /// `.toml` snippet files encountered.
+    pub files_seen: usize,
+    /// How many of those parsed successfully.
+    pub files_parsed: usize,
+    /// Total snippets loaded.
+    pub snippets: usize,
+}
+
+/// Loads every `.toml` snippet file in `dir`, keyed by each command's
+/// uniqueness key (its `id` when present, else its description).
+///
+/// Files that fail to parse are skipped with a warning unless `strict` is
+/// set, in which case the first bad file aborts the load. Duplicate
+/// keys always abort, since the key is how a selection maps back to a
+/// command.
+///
+/// Symlinked snippet files and (when `recursive` is set) symlinked
+/// directories are followed. Each directory's canonical path is visited at
+/// most once, so symlink loops terminate instead of recursing forever.
+///
+/// Files are read strictly sequentially — one open handle at a time, each
+/// closed before the next is opened — so a huge snippet library never
+/// bumps into a low file-descriptor limit.
+#[allow(clippy::too_many_arguments)]
+pub fn load_commands(
+    dir: &Path,
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+    lenient: bool,
+    max_depth: Option<usize>,
+) -> Result<BTreeMap<String, CommandDef>, LoaderError> {
+    Ok(load_commands_with_summary(dir, strict, recursive, policy, lenient, max_depth, &[])?.0)
+}
+
+/// Like [`load_commands`], but also reports what the scan saw, for
+/// callers that want to explain an empty result, and skips any directory
+/// in `exclude` (compared canonically) during the walk.
+#[allow(clippy::too_many_arguments)]
+pub fn load_commands_with_summary(
+    dir: &Path,
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+    lenient: bool,
+    max_depth: Option<usize>,
+    exclude: &[PathBuf],
+) -> Result<(BTreeMap<String, CommandDef>, LoadSummary), LoaderError> {
+    let mut commands = BTreeMap::new();
+    let mut summary = LoadSummary::default();
+    if !dir.is_dir() {
+        return Ok((commands, summary));
+    }
+    let mut visited = HashSet::new();
+    if let Ok(canonical) = dir.canonicalize() {
+        visited.insert(canonical);
+    }
+    // Excluded directories are seeded as already visited, so the walk
+    // never descends into them. Canonical paths mean a symlinked or
+    // relative spelling still matches.
+    for excluded in exclude {
+        if let Ok(canonical) = excluded.canonicalize() {
+            visited.insert(canonical);
+        }
+    }
+    scan_dir(
+        dir,
+        strict,
+        recursive,
+        policy,
+        lenient,
+        1,
+        max_depth,
+        &mut visited,
+        &mut commands,
+        &mut summary,
+    )?;
+    summary.snippets = commands.len();
+    Ok((commands, summary))
+}
+
+/// Parses a snippet file. The canonical form uses `[[commands]]`, but files
+/// generated by other tools sometimes use a different root key; any file
+/// whose root is a single array of command tables is accepted, with the
+/// array treated as the command list. TOML has no bare root arrays, so this
+/// is the closest equivalent.
+fn parse_file(contents: &str, lenient: bool) -> Result<FileDef, toml::de::Error> {
+    let first_error = match toml::from_str::<FileDef>(contents) {
+        Ok(file_def) => return Ok(file_def),
+        Err(err) => err,
+    };
+    if lenient {
+        if let Ok(lenient_def) = toml::from_str::<LenientFileDef>(contents) {
+            return Ok(FileDef {
+                meta: lenient_def.meta,
+                commands: lenient_def
+                    .commands
+                    .into_iter()
+                    .map(CommandSnippet::from)
+                    .collect(),
+            });
+        }
+    }
+    if let Ok(toml::Value::Table(table)) = toml::from_str::<toml::Value>(contents) {
+        if table.len() == 1 {
+            if let Some((_, value @ toml::Value::Array(_))) = table.into_iter().next() {
+                if let Ok(commands) = value.try_into::<Vec<CommandSnippet>>() {
+                    return Ok(FileDef { meta: None, commands });
+                }
+            }
+        }
+    }
+    Err(first_error)
+}
+
+#[allow(clippy::too_many_arguments)]
+fn scan_dir(
+    dir: &Path,
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+    lenient: bool,
+    depth: usize,
+    max_depth: Option<usize>,
+    visited: &mut HashSet<PathBuf>,
+    commands: &mut BTreeMap<String, CommandDef>,
+    summary: &mut LoadSummary,
+) -> Result<(), LoaderError> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|source| LoaderError::Io {
+            message: format!("Could not read directory {}", dir.display()),
+            source,
+        })?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() {
+            // Depth 1 is this directory itself, so a limit of 1 means no
+            // descent at all.
+            if recursive && max_depth.is_none_or(|limit| depth < limit) {
+                // Canonicalize so a symlinked directory pointing back into
+                // the tree is only scanned once.
+                let Ok(canonical) = path.canonicalize() else {
+                    continue; // broken symlink
+                };
+                if visited.insert(canonical) {
+                    scan_dir(
+                        &path,
+                        strict,
+                        recursive,
+                        policy,
+                        lenient,
+                        depth + 1,
+                        max_depth,
+                        visited,
+                        commands,
+                        summary,
+                    )?;
+                }
+            }
+            continue;
+        }
+        if path.extension().and_then(|ext| ext.to_str()) != Some("toml") {
+            continue;
+        }
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(TAXONOMY_FILE) => continue, // the tag taxonomy, not a snippet file
+            Some(DIR_CONFIG_FILE) => continue, // per-directory config, not a snippet file
+            _ => {}
+        }
+        if !path.is_file() {
+            continue;
+        }
+        load_file_into(&path, strict, policy, lenient, commands, summary)?;
+    }
+    Ok(())
+}
+
+/// The per-directory tag taxonomy file name. Excluded from snippet
+/// scanning.
+pub const TAXONOMY_FILE: &str = "tags.toml";
+
+/// The per-directory config file name (see `config::DirConfig`). Excluded
+/// from snippet scanning.
+pub const DIR_CONFIG_FILE: &str = ".cmdy-dir.toml";
+
+/// Loads the optional `tags.toml` taxonomy from `dir`: a flat table of
+/// `tag = "description"` pairs. A missing file is an empty taxonomy; a
+/// malformed one is skipped with a warning, since the taxonomy is purely
+/// informational.
+pub fn load_tag_taxonomy(dir: &Path) -> HashMap<String, String> {
+    let path = dir.join(TAXONOMY_FILE);
+    let Ok(contents) = fs::read_to_string(&path) else {
+        return HashMap::new();
+    };
+    match toml::from_str(&contents) {
+        Ok(taxonomy) => taxonomy,
+        Err(err) => {
+            eprintln!("Warning: skipping {}: {err}", path.display());
+            HashMap::new()
+        }
+    }
+}
+
+/// Loads a single snippet file, keyed like [`load_commands`]. Used by
+/// `--file` to bypass directory scanning entirely.
+pub fn load_file(
+    path: &Path,
+    strict: bool,
+    policy: DuplicatePolicy,
+    lenient: bool,
+) -> Result<BTreeMap<String, CommandDef>, LoaderError> {
+    let mut commands = BTreeMap::new();
+    load_file_into(
+        path,
+        strict,
+        policy,
+        lenient,
+        &mut commands,
+        &mut LoadSummary::default(),
+    )?;
+    Ok(commands)
+}
+
+/// Strips a leading UTF-8 BOM and normalizes CRLF line endings, so snippet
+/// files written on Windows parse the same as everything else.
+fn normalize_contents(contents: &str) -> String {
+    contents
+        .strip_prefix('\u{feff}')
+        .unwrap_or(contents)
+        .replace("\r\n", "\n")
+}
+
+/// The 1-based line numbers of the snippet headers in a file, in order:
+/// any `[[...]]` line, so both the canonical `[[commands]]` form and the
+/// array-root form are covered. The Nth snippet gets the Nth header.
+fn snippet_lines(contents: &str) -> Vec<usize> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| {
+            let line = line.trim();
+            line.starts_with("[[") && line.ends_with("]]")
+        })
+        .map(|(index, _)| index + 1)
+        .collect()
+}
+
+/// The per-file parse-and-merge step shared by the directory scan and
+/// `load_file`.
+#[allow(clippy::too_many_arguments)]
+fn load_file_into(
+    path: &Path,
+    strict: bool,
+    policy: DuplicatePolicy,
+    lenient: bool,
+    commands: &mut BTreeMap<String, CommandDef>,
+    summary: &mut LoadSummary,
+) -> Result<(), LoaderError> {
+    summary.files_seen += 1;
+    let contents = fs::read_to_string(path).map_err(|source| LoaderError::Io {
+        message: format!("Could not read {}", path.display()),
+        source,
+    })?;
+    let contents = normalize_contents(&contents);
+    if lenient
+        && toml::from_str::<FileDef>(&contents).is_err()
+        && toml::from_str::<LenientFileDef>(&contents).is_ok()
+    {
+        eprintln!("Warning: {} has unknown fields (ignored)", path.display());
+    }
+    let file_def: FileDef = match parse_file(&contents, lenient) {
+        Ok(file_def) => file_def,
+        Err(err) => {
+            if strict {
+                return Err(LoaderError::Parse {
+                    file: path.to_path_buf(),
+                    source: err,
+                });
+            }
+            eprintln!("Warning: skipping {}: {err}", path.display());
+            return Ok(());
+        }
+    };
+    summary.files_parsed += 1;
+    let lines = snippet_lines(&contents);
+    let meta = file_def.meta.unwrap_or_default();
+    for (index, snippet) in file_def.commands.into_iter().enumerate() {
+        let has_id = snippet.id.is_some();
+        let line = lines.get(index).copied().unwrap_or(1);
+        let mut def = snippet.into_def(path.to_path_buf(), line);
+        // File-level tags apply to every command, without duplicating
+        // ones the snippet already carries.
+        for tag in &meta.tags {
+            if !def.tags.contains(tag) {
+                def.tags.push(tag.clone());
+            }
+        }
+        let key = def.key().to_string();
+        if def.command.trim().is_empty() {
+            // An empty command "succeeds" instantly when run; that's never
+            // what was meant, so call it out at load time.
+            eprintln!(
+                "Warning: {key:?} in {} has an empty command",
+                path.display()
+            );
+        }
+        if let Some(existing) = commands.get(&key) {
+            match policy {
+                DuplicatePolicy::Error => {
+                    let kind = if has_id { "id" } else { "description" };
+                    // Two snippets in the same file is a different mistake
+                    // than two files colliding; say so.
+                    if existing.source_file == path {
+                        return Err(LoaderError::DuplicateInFile {
+                            kind,
+                            name: key,
+                            file: path.to_path_buf(),
+                        });
+                    }
+                    return Err(LoaderError::Duplicate {
+                        kind,
+                        name: key,
+                        first: existing.source_file.clone(),
+                        second: path.to_path_buf(),
+                    });
+                }
+                DuplicatePolicy::First => {
+                    eprintln!(
+                        "Note: keeping {key:?} from {}; ignoring the one in {}",
+                        existing.source_file.display(),
+                        path.display()
+                    );
+                    continue;
+                }
+                DuplicatePolicy::Last => {
+                    eprintln!(
+                        "Note: {key:?} from {} replaces the one in {}",
+                        path.display(),
+                        existing.source_file.display()
+                    );
+                }
+            }
+        }
+        commands.insert(key, def);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn write_snippet(dir: &Path, name: &str, contents: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn loads_commands_from_toml_files() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "git.toml",
+            r#"
+            [[commands]]
+            description = "Show git status"
+            command = "git status"
+            tags = ["git"]
+            "#,
+        );
+        let commands = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        let def = &commands["Show git status"];
+        assert_eq!(def.command, "git status");
+        assert_eq!(def.tags, vec!["git"]);
+    }
+
+    #[test]
+    fn missing_directory_loads_nothing() {
+        let commands =
+            load_commands(Path::new("/no/such/dir/anywhere"), false, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn non_toml_files_are_ignored() {
+        let dir = tempdir().unwrap();
+        write_snippet(dir.path(), "notes.txt", "not a snippet");
+        let commands = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(commands.is_empty());
+    }
+
+    #[test]
+    fn duplicate_descriptions_error() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"true\"\n",
+        );
+        write_snippet(
+            dir.path(),
+            "b.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"false\"\n",
+        );
+        let err = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap_err();
+        assert!(err.to_string().contains("Duplicate command description"));
+        assert!(matches!(err, LoaderError::Duplicate { .. }));
+    }
+
+    #[test]
+    fn duplicates_within_one_file_get_their_own_message() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"true\"\n\n[[commands]]\ndescription = \"Dupe\"\ncommand = \"false\"\n",
+        );
+        let err = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap_err();
+        assert!(matches!(err, LoaderError::DuplicateInFile { .. }));
+        assert!(err.to_string().contains("defined twice in"));
+    }
+
+    #[test]
+    fn parse_failures_are_distinguishable_from_duplicates() {
+        let dir = tempdir().unwrap();
+        write_snippet(dir.path(), "bad.toml", "this is not [ valid toml");
+        let err = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap_err();
+        assert!(matches!(err, LoaderError::Parse { .. }));
+    }
+
+    #[test]
+    fn bad_files_are_skipped_unless_strict() {
+        let dir = tempdir().unwrap();
+        write_snippet(dir.path(), "bad.toml", "this is not [ valid toml");
+        write_snippet(
+            dir.path(),
+            "good.toml",
+            "[[commands]]\ndescription = \"Good\"\ncommand = \"true\"\n",
+        );
+        let commands = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).is_err());
+    }
+
+    #[test]
+    fn subdirectories_require_recursive() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        write_snippet(
+            &sub,
+            "nested.toml",
+            "[[commands]]\ndescription = \"Nested\"\ncommand = \"true\"\n",
+        );
+        assert!(load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap().is_empty());
+        assert_eq!(load_commands(dir.path(), false, true, DuplicatePolicy::Error, false, None).unwrap().len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinked_snippet_files_are_followed() {
+        let storage = tempdir().unwrap();
+        let real = write_snippet(
+            storage.path(),
+            "real.toml",
+            "[[commands]]\ndescription = \"Linked\"\ncommand = \"true\"\n",
+        );
+        let dir = tempdir().unwrap();
+        std::os::unix::fs::symlink(&real, dir.path().join("link.toml")).unwrap();
+        let commands = load_commands(dir.path(), false, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("Linked"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlink_loops_terminate() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        fs::create_dir(&sub).unwrap();
+        write_snippet(
+            &sub,
+            "nested.toml",
+            "[[commands]]\ndescription = \"Nested\"\ncommand = \"true\"\n",
+        );
+        // A symlink pointing back at the root creates a cycle.
+        std::os::unix::fs::symlink(dir.path(), sub.join("loop")).unwrap();
+        let commands = load_commands(dir.path(), false, true, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+    }
+
+    #[test]
+    fn array_root_form_is_accepted() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "generated.toml",
+            "[[snippets]]\ndescription = \"From elsewhere\"\ncommand = \"true\"\n",
+        );
+        let commands = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("From elsewhere"));
+    }
+
+    #[test]
+    fn array_root_form_still_denies_unknown_fields() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "generated.toml",
+            "[[snippets]]\ndescription = \"X\"\ncommand = \"true\"\nbogus = 1\n",
+        );
+        assert!(load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).is_err());
+    }
+
+    #[test]
+    fn load_file_reads_exactly_one_file() {
+        let dir = tempdir().unwrap();
+        let wanted = write_snippet(
+            dir.path(),
+            "wanted.toml",
+            "[[commands]]\ndescription = \"Wanted\"\ncommand = \"true\"\n",
+        );
+        write_snippet(
+            dir.path(),
+            "other.toml",
+            "[[commands]]\ndescription = \"Other\"\ncommand = \"true\"\n",
+        );
+        let commands = load_file(&wanted, true, DuplicatePolicy::Error, false).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("Wanted"));
+    }
+
+    #[test]
+    fn load_file_errors_on_a_missing_file() {
+        assert!(load_file(Path::new("/no/such/file.toml"), false, DuplicatePolicy::Error, false).is_err());
+    }
+
+    #[test]
+    fn ids_allow_repeated_descriptions() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"Deploy\"\ncommand = \"deploy dev\"\nid = \"deploy-dev\"\n\n[[commands]]\ndescription = \"Deploy\"\ncommand = \"deploy prod\"\nid = \"deploy-prod\"\n",
+        );
+        let commands = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert!(commands.contains_key("deploy-dev"));
+        assert!(commands.contains_key("deploy-prod"));
+    }
+
+    #[test]
+    fn duplicate_ids_error() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"A\"\ncommand = \"true\"\nid = \"same\"\n\n[[commands]]\ndescription = \"B\"\ncommand = \"true\"\nid = \"same\"\n",
+        );
+        let err = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap_err();
+        assert!(err.to_string().contains("Duplicate command id"));
+    }
+
+    #[test]
+    fn missing_descriptions_derive_from_comment_or_command() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "derived.toml",
+            "[[commands]]\ncommand = \"# Restart the proxy\\nsystemctl restart proxy\"\n\n[[commands]]\ncommand = \"git pull --rebase\"\n",
+        );
+        let commands = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(commands.contains_key("Restart the proxy"));
+        assert!(commands.contains_key("git pull --rebase"));
+    }
+
+    #[test]
+    fn derived_descriptions_truncate_long_commands() {
+        let long = "x".repeat(80);
+        let snippet: FileDef =
+            toml::from_str(&format!("[[commands]]\ncommand = \"{long}\"\n")).unwrap();
+        let derived = snippet.commands[0].derived_description();
+        assert_eq!(derived.chars().count(), 61);
+        assert!(derived.ends_with('…'));
+    }
+
+    #[test]
+    fn confirm_accepts_a_bool() {
+        let file_def: FileDef = toml::from_str(
+            "[[commands]]\ndescription = \"X\"\ncommand = \"true\"\nconfirm = true\n",
+        )
+        .unwrap();
+        let confirm = &file_def.commands[0].confirm;
+        assert!(confirm.is_required());
+        assert_eq!(confirm.prompt("true"), "Run `true`?");
+    }
+
+    #[test]
+    fn confirm_accepts_a_custom_message() {
+        let file_def: FileDef = toml::from_str(
+            "[[commands]]\ndescription = \"X\"\ncommand = \"true\"\nconfirm = \"Deletes prod. Continue?\"\n",
+        )
+        .unwrap();
+        let confirm = &file_def.commands[0].confirm;
+        assert!(confirm.is_required());
+        assert_eq!(confirm.prompt("true"), "Deletes prod. Continue?");
+    }
+
+    #[test]
+    fn confirm_defaults_to_off() {
+        let file_def: FileDef = toml::from_str(
+            "[[commands]]\ndescription = \"X\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        assert!(!file_def.commands[0].confirm.is_required());
+    }
+
+    #[test]
+    fn bom_and_crlf_files_load_cleanly() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "windows.toml",
+            "\u{feff}[[commands]]\r\ndescription = \"From Windows\"\r\ncommand = \"true\"\r\n",
+        );
+        let commands = load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("From Windows"));
+    }
+
+    #[test]
+    fn taxonomy_maps_tags_to_descriptions() {
+        let dir = tempdir().unwrap();
+        write_snippet(dir.path(), "tags.toml", "git = \"Version control\"\n");
+        let taxonomy = load_tag_taxonomy(dir.path());
+        assert_eq!(taxonomy["git"], "Version control");
+        assert!(load_tag_taxonomy(Path::new("/no/such/dir")).is_empty());
+    }
+
+    #[test]
+    fn taxonomy_file_is_not_scanned_as_snippets() {
+        let dir = tempdir().unwrap();
+        write_snippet(dir.path(), "tags.toml", "git = \"Version control\"\n");
+        assert!(load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn summary_distinguishes_empty_from_unparsable() {
+        let empty = tempdir().unwrap();
+        let (_, summary) =
+            load_commands_with_summary(empty.path(), false, false, DuplicatePolicy::Error, false, None, &[])
+                .unwrap();
+        assert_eq!(summary.files_seen, 0);
+
+        let broken = tempdir().unwrap();
+        write_snippet(broken.path(), "bad.toml", "not [ valid");
+        let (_, summary) =
+            load_commands_with_summary(broken.path(), false, false, DuplicatePolicy::Error, false, None, &[])
+                .unwrap();
+        assert_eq!(summary.files_seen, 1);
+        assert_eq!(summary.files_parsed, 0);
+
+        let good = tempdir().unwrap();
+        write_snippet(
+            good.path(),
+            "good.toml",
+            "[[commands]]\ndescription = \"G\"\ncommand = \"true\"\n",
+        );
+        let (_, summary) =
+            load_commands_with_summary(good.path(), false, false, DuplicatePolicy::Error, false, None, &[])
+                .unwrap();
+        assert_eq!(summary.files_parsed, 1);
+        assert_eq!(summary.snippets, 1);
+    }
+
+    #[test]
+    fn first_policy_keeps_the_earlier_definition() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"first\"\n",
+        );
+        write_snippet(
+            dir.path(),
+            "b.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"last\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::First, false, None).unwrap();
+        assert_eq!(commands["Dupe"].command, "first");
+    }
+
+    #[test]
+    fn last_policy_keeps_the_later_definition() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "a.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"first\"\n",
+        );
+        write_snippet(
+            dir.path(),
+            "b.toml",
+            "[[commands]]\ndescription = \"Dupe\"\ncommand = \"last\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Last, false, None).unwrap();
+        assert_eq!(commands["Dupe"].command, "last");
+    }
+
+    #[test]
+    fn unknown_snippet_fields_are_rejected() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "bad.toml",
+            "[[commands]]\ndescription = \"X\"\ncommand = \"true\"\nbogus = 1\n",
+        );
+        assert!(load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).is_err());
+    }
+
+    #[test]
+    fn empty_commands_still_load() {
+        // The warning is on stderr; what we can assert is that the snippet
+        // isn't dropped, so `check` and `list` can surface it.
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "hollow.toml",
+            "[[commands]]\ndescription = \"Hollow\"\ncommand = \"  \"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(commands.contains_key("Hollow"));
+    }
+
+    #[test]
+    fn success_codes_parse_and_default_to_zero() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "codes.toml",
+            "[[commands]]\ndescription = \"Diff\"\ncommand = \"diff a b\"\nsuccess_codes = [0, 1]\n\n[[commands]]\ndescription = \"Plain\"\ncommand = \"true\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands["Diff"].success_codes, vec![0, 1]);
+        assert_eq!(commands["Plain"].success_codes, vec![0]);
+    }
+
+    #[test]
+    fn excluded_directories_are_skipped_during_the_walk() {
+        let dir = tempdir().unwrap();
+        let noisy = dir.path().join("noisy");
+        fs::create_dir(&noisy).unwrap();
+        write_snippet(
+            dir.path(),
+            "keep.toml",
+            "[[commands]]\ndescription = \"Kept\"\ncommand = \"true\"\n",
+        );
+        write_snippet(
+            &noisy,
+            "skip.toml",
+            "[[commands]]\ndescription = \"Skipped\"\ncommand = \"true\"\n",
+        );
+        let (commands, _) = load_commands_with_summary(
+            dir.path(),
+            true,
+            true,
+            DuplicatePolicy::Error,
+            false,
+            None,
+            &[noisy],
+        )
+        .unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("Kept"));
+    }
+
+    #[test]
+    fn retries_parse_and_default_to_zero() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "flaky.toml",
+            "[[commands]]\ndescription = \"Flaky\"\ncommand = \"curl x\"\nretries = 3\nretry_delay_secs = 2\n\n[[commands]]\ndescription = \"Plain\"\ncommand = \"true\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands["Flaky"].retries, 3);
+        assert_eq!(commands["Flaky"].retry_delay_secs, 2);
+        assert_eq!(commands["Plain"].retries, 0);
+        assert_eq!(commands["Plain"].retry_delay_secs, 0);
+    }
+
+    #[test]
+    fn directories_with_many_files_load_within_fd_limits() {
+        let dir = tempdir().unwrap();
+        // Far more files than a conservative `ulimit -n` allows open at
+        // once; sequential reading must handle this without running out
+        // of descriptors.
+        for n in 0..500 {
+            write_snippet(
+                dir.path(),
+                &format!("bulk-{n:03}.toml"),
+                &format!("[[commands]]\ndescription = \"Bulk {n}\"\ncommand = \"true\"\n"),
+            );
+        }
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands.len(), 500);
+    }
+
+    #[test]
+    fn file_meta_tags_apply_to_every_command() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "work.toml",
+            "[meta]\ntags = [\"work\"]\n\n[[commands]]\ndescription = \"Tagged\"\ncommand = \"true\"\ntags = [\"git\"]\n\n[[commands]]\ndescription = \"Bare\"\ncommand = \"true\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands["Tagged"].tags, vec!["git", "work"]);
+        assert_eq!(commands["Bare"].tags, vec!["work"]);
+    }
+
+    #[test]
+    fn file_meta_still_denies_unknown_fields() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "bad-meta.toml",
+            "[meta]\nbogus = 1\n\n[[commands]]\ndescription = \"X\"\ncommand = \"true\"\n",
+        );
+        assert!(load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).is_err());
+    }
+
+    #[test]
+    fn snippet_lines_point_at_their_headers() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "lines.toml",
+            "# a comment\n\n[[commands]]\ndescription = \"First\"\ncommand = \"true\"\n\n[[commands]]\ndescription = \"Second\"\ncommand = \"true\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert_eq!(commands["First"].line, 3);
+        assert_eq!(commands["Second"].line, 7);
+    }
+
+    #[test]
+    fn enabled_parses_and_defaults_to_true() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "toggle.toml",
+            "[[commands]]\ndescription = \"Off\"\ncommand = \"true\"\nenabled = false\n\n[[commands]]\ndescription = \"On\"\ncommand = \"true\"\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(!commands["Off"].enabled);
+        assert!(commands["On"].enabled);
+    }
+
+    #[test]
+    fn params_parse_with_type_and_required() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "typed.toml",
+            "[[commands]]\ndescription = \"Curl\"\ncommand = \"curl :{{port}}\"\nparams = [{ name = \"port\", type = \"int\", required = true }]\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        let params = &commands["Curl"].params;
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].name, "port");
+        assert_eq!(params[0].param_type, ParamType::Int);
+        assert!(params[0].required);
+    }
+
+    #[test]
+    fn base_inheritance_merges_env_and_prefers_the_child() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "base.toml",
+            "[[commands]]\ndescription = \"Common\"\ncommand = \"true\"\ntags = [\"shared\"]\n[commands.env]\nAPP_ENV = \"prod\"\nREGION = \"us-east-1\"\n\n[[commands]]\ndescription = \"Deploy\"\ncommand = \"deploy\"\nbase = \"Common\"\n[commands.env]\nREGION = \"eu-west-1\"\n",
+        );
+        let mut commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        resolve_bases(&mut commands).unwrap();
+        let deploy = &commands["Deploy"];
+        // Inherited where unset, overridden where set.
+        assert_eq!(deploy.env["APP_ENV"], "prod");
+        assert_eq!(deploy.env["REGION"], "eu-west-1");
+        assert_eq!(deploy.tags, vec!["shared".to_string()]);
+        // The base itself is untouched.
+        assert_eq!(commands["Common"].env["REGION"], "us-east-1");
+    }
+
+    #[test]
+    fn missing_and_cyclic_bases_are_errors() {
+        let mut missing = BTreeMap::new();
+        let mut orphan = CommandDef {
+            description: "Orphan".to_string(),
+            command: "true".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: Some("Nowhere".to_string()),
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        };
+        missing.insert("Orphan".to_string(), orphan.clone());
+        assert!(matches!(
+            resolve_bases(&mut missing),
+            Err(LoaderError::MissingBase { .. })
+        ));
+
+        let mut cyclic = BTreeMap::new();
+        orphan.base = Some("Orphan".to_string());
+        cyclic.insert("Orphan".to_string(), orphan);
+        assert!(matches!(
+            resolve_bases(&mut cyclic),
+            Err(LoaderError::BaseCycle { .. })
+        ));
+    }
+
+    #[test]
+    fn expand_generates_one_entry_per_value() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "macro.toml",
+            "[[commands]]\ndescription = \"Deploy to {{region}}\"\ncommand = \"deploy --region {{region}}\"\n[commands.expand]\nregion = [\"us\", \"eu\"]\n",
+        );
+        let mut commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        expand_macros(&mut commands, 100).unwrap();
+        assert_eq!(commands.len(), 2);
+        assert_eq!(commands["Deploy to us"].command, "deploy --region us");
+        assert_eq!(commands["Deploy to eu"].command, "deploy --region eu");
+    }
+
+    #[test]
+    fn expand_takes_the_cartesian_product_of_two_variables() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "macro.toml",
+            "[[commands]]\ndescription = \"Sync\"\ncommand = \"sync {{env}} {{region}}\"\n[commands.expand]\nenv = [\"dev\", \"prod\"]\nregion = [\"us\", \"eu\"]\n",
+        );
+        let mut commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        expand_macros(&mut commands, 100).unwrap();
+        // The description had no tokens, so combos land in a suffix.
+        assert_eq!(commands.len(), 4);
+        assert_eq!(
+            commands["Sync (env=dev, region=eu)"].command,
+            "sync dev eu"
+        );
+        assert_eq!(
+            commands["Sync (env=prod, region=us)"].command,
+            "sync prod us"
+        );
+        // The cap guards against explosive products.
+        let mut reloaded =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, None).unwrap();
+        assert!(matches!(
+            expand_macros(&mut reloaded, 3),
+            Err(LoaderError::ExpansionOverflow { count: 4, cap: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn max_depth_bounds_recursive_scanning() {
+        let dir = tempdir().unwrap();
+        let sub = dir.path().join("sub");
+        let subsub = sub.join("subsub");
+        fs::create_dir_all(&subsub).unwrap();
+        write_snippet(
+            dir.path(),
+            "top.toml",
+            "[[commands]]\ndescription = \"Top\"\ncommand = \"true\"\n",
+        );
+        write_snippet(
+            &sub,
+            "mid.toml",
+            "[[commands]]\ndescription = \"Mid\"\ncommand = \"true\"\n",
+        );
+        write_snippet(
+            &subsub,
+            "deep.toml",
+            "[[commands]]\ndescription = \"Deep\"\ncommand = \"true\"\n",
+        );
+        let only_top =
+            load_commands(dir.path(), true, true, DuplicatePolicy::Error, false, Some(1))
+                .unwrap();
+        assert_eq!(only_top.len(), 1);
+        let two_levels =
+            load_commands(dir.path(), true, true, DuplicatePolicy::Error, false, Some(2))
+                .unwrap();
+        assert_eq!(two_levels.len(), 2);
+        assert!(two_levels.contains_key("Mid"));
+        let unlimited =
+            load_commands(dir.path(), true, true, DuplicatePolicy::Error, false, None)
+                .unwrap();
+        assert_eq!(unlimited.len(), 3);
+        // Without recursion the limit is moot.
+        let flat =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, false, Some(5))
+                .unwrap();
+        assert_eq!(flat.len(), 1);
+    }
+
+    #[test]
+    fn lenient_mode_ignores_unknown_snippet_fields() {
+        let dir = tempdir().unwrap();
+        write_snippet(
+            dir.path(),
+            "newer.toml",
+            "[[commands]]\ndescription = \"X\"\ncommand = \"true\"\nfrom_the_future = 1\n",
+        );
+        let commands =
+            load_commands(dir.path(), true, false, DuplicatePolicy::Error, true, None).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert!(commands.contains_key("X"));
+    }
+}
@@ -0,0 +1,2698 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+
+#[cfg(feature = "remotes")]
+use cmdy::remotes;
+#[cfg(feature = "serve")]
+use cmdy::serve;
+#[cfg(feature = "tui")]
+use cmdy::tui;
+use cmdy::{clipboard, config, exec, history, loader, ui, usage};
+use cmdy::{AppConfig, CommandDef, DuplicatePolicy};
+
+#[derive(Debug, Parser)]
+#[command(name = "cmdy", version, about = "Your friendly command manager")]
+struct CliArgs {
+    /// Scan this directory instead of the configured ones (repeatable)
+    #[arg(short, long = "dir")]
+    dirs: Vec<PathBuf>,
+
+    /// Load commands from exactly this file, skipping directory scanning
+    #[arg(short, long)]
+    file: Option<PathBuf>,
+
+    /// Only show commands with this tag (repeatable or comma-separated)
+    #[arg(short, long = "tag", value_delimiter = ',')]
+    tags: Vec<String>,
+
+    /// Hide commands with this tag (repeatable or comma-separated)
+    #[arg(long = "not-tag", value_delimiter = ',')]
+    not_tags: Vec<String>,
+
+    /// Only show commands with a tag starting with this prefix,
+    /// case-insensitive (repeatable or comma-separated)
+    #[arg(long = "tag-prefix", value_delimiter = ',', value_name = "PREFIX")]
+    tag_prefixes: Vec<String>,
+
+    /// Read additional tag filters from a file (one tag per line, `#`
+    /// comments allowed)
+    #[arg(long, value_name = "FILE")]
+    tag_file: Option<PathBuf>,
+
+    /// Start the picker with this query
+    #[arg(short, long)]
+    query: Option<String>,
+
+    /// Clear the query remembered by `remember_query`
+    #[arg(long)]
+    forget: bool,
+
+    /// Skip the picker and run the best --query match directly
+    #[arg(long)]
+    first: bool,
+
+    /// Skip the picker and run a random command (after filters)
+    #[arg(long, conflicts_with = "first")]
+    random: bool,
+
+    /// Re-run the most recently executed command
+    #[arg(long, conflicts_with_all = ["first", "random"])]
+    last: bool,
+
+    /// Provide a placeholder value without prompting (repeatable)
+    #[arg(long = "var", value_name = "KEY=VALUE")]
+    vars: Vec<String>,
+
+    /// Re-run the selection on this interval, like watch(1) (e.g. "5s",
+    /// "2m"); only with `run` or --first. Ctrl-C exits
+    #[arg(long, value_name = "INTERVAL")]
+    watch: Option<String>,
+
+    /// With --watch: stop after this many runs
+    #[arg(long, value_name = "N", requires = "watch")]
+    watch_count: Option<u64>,
+
+    /// Suppress informational acknowledgments on stderr
+    #[arg(long)]
+    quiet: bool,
+
+    /// Limit recursive scanning to this many directory levels (1 = only
+    /// the top directory); overrides the config
+    #[arg(long, value_name = "N")]
+    max_depth: Option<usize>,
+
+    /// Show what would run without executing it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Break the selection down (resolved command, env, cwd, prompts,
+    /// required binaries) without executing it
+    #[arg(long)]
+    explain: bool,
+
+    /// Open the selected command in your editor and run what you save
+    /// instead of the stored command
+    #[arg(long)]
+    edit_before_run: bool,
+
+    /// Print the effective configuration as TOML and exit
+    #[arg(long)]
+    dump_config: bool,
+
+    /// With --dry-run: offer to run the command after showing it
+    #[arg(long, requires = "dry_run")]
+    interactive: bool,
+
+    /// Output format for --dry-run (human-readable when absent)
+    #[arg(long, value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Emit machine-readable JSON for list, search, tags, dry-run, and
+    /// errors
+    #[arg(long)]
+    json: bool,
+
+    /// Line template for list/search output; tokens {description},
+    /// {command}, {tags}, {file}, plus literal \t and \n
+    #[arg(long, value_name = "TEMPLATE")]
+    output_template: Option<String>,
+
+    /// Print the selected command to stdout instead of executing it
+    #[arg(long)]
+    print_command: bool,
+
+    /// Print the selected command's source file path instead of executing
+    #[arg(long)]
+    print_path: bool,
+
+    /// Print `path:line` of the selected command's definition instead of
+    /// executing, for editors jumping to the snippet
+    #[arg(long)]
+    print_file_and_line: bool,
+
+    /// With fzf, auto-pick a query that matches exactly one command
+    /// (passes `--select-1 --exit-0`; no effect on other filters)
+    #[arg(long = "select-1")]
+    select_1: bool,
+
+    /// Run this shell template on the selection instead of executing it;
+    /// {} is the command and {file} its source file
+    #[arg(long = "exec", value_name = "TEMPLATE")]
+    exec_template: Option<String>,
+
+    /// Use this config profile (<profile>.toml, commands-<profile>/);
+    /// overrides $CMDY_PROFILE
+    #[arg(long)]
+    profile: Option<String>,
+
+    /// Error on snippet files that fail to parse instead of skipping them
+    #[arg(long)]
+    strict: bool,
+
+    /// Always ask before running, even for snippets without `confirm`
+    #[arg(long)]
+    confirm: bool,
+
+    /// Append the executed command to your shell history
+    #[arg(long)]
+    history: bool,
+
+    /// Run as a long-lived JSON backend on stdin/stdout
+    #[cfg(feature = "serve")]
+    #[arg(long)]
+    serve: bool,
+
+    /// Browse commands in the built-in multi-pane TUI instead of the
+    /// external filter program
+    #[cfg(feature = "tui")]
+    #[arg(long)]
+    tui: bool,
+
+    /// Show at most this many commands, applied after filtering and
+    /// sorting
+    #[arg(short, long, value_name = "N")]
+    limit: Option<usize>,
+
+    /// Sort order for the picker and list output
+    #[arg(short, long, value_enum, default_value_t = SortMode::Alphabetical)]
+    sort: SortMode,
+
+    /// Reverse the sort order
+    #[arg(short, long)]
+    reverse: bool,
+
+    #[command(subcommand)]
+    action: Option<Action>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// A machine-readable JSON object
+    Json,
+    /// Aligned columns for human scanning (list only)
+    Table,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum SortMode {
+    /// A to Z by description
+    Alphabetical,
+    /// Most-used first
+    Frequency,
+    /// Highest priority field first
+    Priority,
+}
+
+#[derive(Debug, Subcommand)]
+enum Action {
+    /// Run a command by its description (fuzzy unless --exact)
+    Run {
+        name: String,
+        /// Require an exact description match instead of fuzzy lookup
+        #[arg(long)]
+        exact: bool,
+        /// Extra arguments appended to the command (quote-safe); usually
+        /// given after `--`
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    /// List all commands
+    List {
+        /// Include snippets disabled with `enabled = false`, marked as such
+        #[arg(long)]
+        all: bool,
+    },
+    /// List commands whose description matches a term
+    Search { term: String },
+    /// List every tag in use
+    Tags,
+    /// Validate all snippet files
+    Check {
+        /// Also run each command through `sh -n` to catch shell syntax
+        /// errors (spawns a shell per snippet)
+        #[arg(long)]
+        check_syntax: bool,
+        /// Also warn about commands that invoke sudo without a `confirm`
+        /// prompt
+        #[arg(long)]
+        warn_sudo: bool,
+    },
+    /// Copy the selected command to the clipboard
+    Clip,
+    /// Open the selected command's file in your editor
+    Edit,
+    /// Reveal the selected command's file in the OS file manager
+    Open,
+    /// Show cmdy's own log of executed commands
+    Log {
+        /// How many recent records to show
+        #[arg(long, default_value_t = 20)]
+        last: usize,
+    },
+    /// Diagnose common setup problems
+    Doctor,
+    /// Inspect the cmdy.toml config itself
+    #[command(subcommand)]
+    Config(ConfigAction),
+    /// Bootstrap snippets from your shell history
+    ImportHistory {
+        /// How many recent unique commands to import
+        #[arg(long, default_value_t = 50)]
+        last: usize,
+        /// Append to imported.toml in the commands directory instead of
+        /// printing
+        #[arg(long)]
+        write: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ConfigAction {
+    /// Validate cmdy.toml: parse errors, missing directories, and a
+    /// filter command that isn't on PATH all fail the check
+    Check,
+}
+
+/// The directories to scan: just the `--dir` flags when any are given,
+/// otherwise the default commands directory plus any configured extras.
+///
+/// Paths are canonicalized and deduplicated, so the same directory reached
+/// via different spellings (relative vs. absolute, trailing `.`, symlinks)
+/// is only scanned once. Paths that don't exist are skipped rather than
+/// treated as errors.
+fn get_scan_dirs(cli_args: &CliArgs, config: &AppConfig) -> Result<Vec<PathBuf>> {
+    let candidates = if !cli_args.dirs.is_empty() {
+        cli_args.dirs.clone()
+    } else {
+        let mut dirs = vec![config::get_commands_dir()?];
+        dirs.extend(config.directories.iter().map(|dir| config::expand_path(dir)));
+        dirs
+    };
+    let excluded: Vec<PathBuf> = config
+        .exclude_directories
+        .iter()
+        .filter_map(|dir| config::expand_path(dir).canonicalize().ok())
+        .collect();
+    let mut dirs = Vec::new();
+    for candidate in candidates {
+        let Ok(canonical) = candidate.canonicalize() else {
+            continue; // doesn't exist (yet); nothing to scan
+        };
+        if !excluded.contains(&canonical) && !dirs.contains(&canonical) {
+            dirs.push(canonical);
+        }
+    }
+    Ok(dirs)
+}
+
+fn main() {
+    let cli_args = CliArgs::parse();
+    let json = cli_args.json;
+    if let Err(err) = run(cli_args) {
+        if json {
+            eprintln!(
+                "{}",
+                serde_json::json!({
+                    "error": format!("{err:#}"),
+                    "kind": error_kind(&err),
+                })
+            );
+        } else {
+            eprintln!("Error: {err:?}");
+        }
+        std::process::exit(1);
+    }
+}
+
+/// Classifies a top-level error for `--json` consumers: loader errors map
+/// to stable kind strings; anything else is `"other"`. Tooling can branch
+/// on the kind without parsing the human message.
+fn error_kind(err: &anyhow::Error) -> &'static str {
+    match err.downcast_ref::<loader::LoaderError>() {
+        Some(loader::LoaderError::Io { .. }) => "io",
+        Some(loader::LoaderError::Parse { .. }) => "parse",
+        Some(loader::LoaderError::Duplicate { .. })
+        | Some(loader::LoaderError::DuplicateInFile { .. }) => "duplicate",
+        Some(loader::LoaderError::MissingBase { .. })
+        | Some(loader::LoaderError::BaseCycle { .. }) => "base",
+        Some(loader::LoaderError::ExpansionOverflow { .. }) => "expansion",
+        None => "other",
+    }
+}
+
+fn run(mut cli_args: CliArgs) -> Result<()> {
+    if let Some(tag_file) = &cli_args.tag_file {
+        // Merged into --tag before any filtering, so a shared filter set
+        // behaves exactly like spelling the flags out.
+        let tags = read_tag_file(tag_file)?;
+        cli_args.tags.extend(tags);
+    }
+    if let Some(profile) = &cli_args.profile {
+        // config.rs reads the profile from the environment, so the flag
+        // just becomes the variable.
+        env::set_var("CMDY_PROFILE", profile);
+    }
+    let mut config = config::load_app_config();
+    // A `.cmdy-dir.toml` in an explicit --dir directory can tune the
+    // picker for that set of snippets.
+    config::apply_dir_overrides(&mut config, &cli_args.dirs);
+    if cli_args.select_1 {
+        config.auto_select_unique = true;
+    }
+    let scan_dirs = get_scan_dirs(&cli_args, &config)?;
+
+    if cli_args.dump_config {
+        print!("{}", dump_config(&config)?);
+        return Ok(());
+    }
+
+    #[cfg(feature = "serve")]
+    if cli_args.serve {
+        return serve::serve(
+            &scan_dirs,
+            cli_args.strict,
+            config.recursive,
+            config.duplicate_policy,
+        );
+    }
+
+    if let Some(Action::Config(ConfigAction::Check)) = &cli_args.action {
+        return config_check(cli_args.quiet);
+    }
+
+    if let Some(Action::Log { last }) = &cli_args.action {
+        return show_log(*last);
+    }
+
+    if let Some(Action::ImportHistory { last, write }) = &cli_args.action {
+        return import_history(*last, *write, cli_args.quiet);
+    }
+
+    // Watching an interactive pick would re-open the picker every cycle;
+    // only the non-interactive selection paths make sense.
+    if cli_args.watch.is_some()
+        && !cli_args.first
+        && !matches!(cli_args.action, Some(Action::Run { .. }))
+    {
+        bail!("--watch requires `run <name>` or --first");
+    }
+
+    let exclude_dirs: Vec<PathBuf> = config
+        .exclude_directories
+        .iter()
+        .map(|dir| config::expand_path(dir))
+        .collect();
+    let mut commands: BTreeMap<String, CommandDef> = BTreeMap::new();
+    let mut summary = loader::LoadSummary::default();
+    if let Some(file) = &cli_args.file {
+        if !file.is_file() {
+            bail!("No such file {}", file.display());
+        }
+        commands = loader::load_file(
+            file,
+            cli_args.strict,
+            config.duplicate_policy,
+            config.allow_unknown_fields,
+        )?;
+        summary.files_seen = 1;
+        summary.files_parsed = 1;
+        summary.snippets = commands.len();
+    } else {
+        for dir in &scan_dirs {
+            let (loaded, dir_summary) = loader::load_commands_with_summary(
+                dir,
+                cli_args.strict,
+                config.recursive,
+                config.duplicate_policy,
+                config.allow_unknown_fields,
+                cli_args.max_depth.or(config.max_depth),
+                &exclude_dirs,
+            )?;
+            summary.files_seen += dir_summary.files_seen;
+            summary.files_parsed += dir_summary.files_parsed;
+            summary.snippets += dir_summary.snippets;
+            merge_commands(&mut commands, loaded, config.duplicate_policy)?;
+        }
+        #[cfg(feature = "remotes")]
+        for path in remotes::fetch_remotes(&config.remotes) {
+            let loaded = loader::load_file(
+                &path,
+                cli_args.strict,
+                config.duplicate_policy,
+                config.allow_unknown_fields,
+            )?;
+            summary.files_seen += 1;
+            summary.files_parsed += 1;
+            summary.snippets += loaded.len();
+            merge_commands(&mut commands, loaded, config.duplicate_policy)?;
+        }
+        #[cfg(not(feature = "remotes"))]
+        if !config.remotes.is_empty() {
+            eprintln!("Note: this build has no remotes support; ignoring `remotes`");
+        }
+    }
+    loader::expand_macros(&mut commands, config.expand_cap)?;
+    loader::resolve_bases(&mut commands)?;
+
+    let mut commands_vec: Vec<CommandDef> = commands.into_values().collect();
+    // Disabled snippets stay out of the picker and run lookups; only
+    // `list --all` wants to see them.
+    if !matches!(&cli_args.action, Some(Action::List { all: true })) {
+        commands_vec.retain(|def| def.enabled);
+    }
+    if !cli_args.tags.is_empty() {
+        commands_vec.retain(|def| {
+            cli_args
+                .tags
+                .iter()
+                .all(|pattern| def.tags.iter().any(|tag| tag_matches(pattern, tag)))
+        });
+    }
+    if !cli_args.not_tags.is_empty() {
+        commands_vec.retain(|def| {
+            !cli_args
+                .not_tags
+                .iter()
+                .any(|pattern| def.tags.iter().any(|tag| tag_matches(pattern, tag)))
+        });
+    }
+    if !cli_args.tag_prefixes.is_empty() {
+        commands_vec.retain(|def| {
+            cli_args
+                .tag_prefixes
+                .iter()
+                .all(|prefix| has_tag_prefix(def, prefix))
+        });
+    }
+    sort_commands(&mut commands_vec, cli_args.sort, cli_args.reverse);
+    float_favorites(&mut commands_vec, &config.favorite_tag);
+    limit_commands(&mut commands_vec, cli_args.limit);
+
+    if cli_args.forget {
+        usage::save_last_query(None);
+    } else if config.remember_query {
+        if let Some(query) = &cli_args.query {
+            usage::save_last_query(Some(query));
+        }
+    }
+
+    // A default that matches no placeholder is almost certainly a typo on
+    // one side; flag it early instead of at prompt time.
+    for def in &commands_vec {
+        for finding in placeholder_findings(def, false) {
+            eprintln!("Warning: {finding}");
+        }
+    }
+
+    let empty = empty_message(
+        !scan_dirs.is_empty() || cli_args.file.is_some(),
+        &summary,
+    );
+
+    match &cli_args.action {
+        Some(Action::List { .. }) => {
+            if cli_args.json {
+                println!("{}", commands_json(commands_vec.iter())?);
+            } else if cli_args.format == Some(OutputFormat::Table) {
+                print!("{}", list_table(&commands_vec, terminal_width()));
+            } else {
+                for def in &commands_vec {
+                    if def.enabled {
+                        println!("{}", output_line(def, &cli_args));
+                    } else {
+                        println!("{} (disabled)", output_line(def, &cli_args));
+                    }
+                }
+            }
+        }
+        Some(Action::Search { term }) => {
+            let term = term.to_lowercase();
+            let matches = commands_vec
+                .iter()
+                .filter(|def| def.description.to_lowercase().contains(&term));
+            if cli_args.json {
+                println!("{}", commands_json(matches)?);
+            } else {
+                for def in matches {
+                    println!("{}", output_line(def, &cli_args));
+                }
+            }
+        }
+        Some(Action::Tags) => {
+            let mut all_tags = BTreeSet::new();
+            for def in &commands_vec {
+                all_tags.extend(def.tags.iter().cloned());
+            }
+            let mut taxonomy = std::collections::HashMap::new();
+            for dir in &scan_dirs {
+                taxonomy.extend(loader::load_tag_taxonomy(dir));
+            }
+            if cli_args.json {
+                let tags: Vec<&String> = all_tags.iter().collect();
+                println!("{}", serde_json::to_string(&tags)?);
+            } else if taxonomy.is_empty() {
+                for tag in all_tags {
+                    println!("{tag}");
+                }
+            } else {
+                for tag in all_tags {
+                    let description = taxonomy
+                        .get(&tag)
+                        .map(String::as_str)
+                        .unwrap_or("(no description)");
+                    println!("{tag}\t{description}");
+                }
+            }
+        }
+        Some(Action::Check {
+            check_syntax,
+            warn_sudo,
+        }) => {
+            let mut count = 0;
+            let mut violations = Vec::new();
+            for dir in &scan_dirs {
+                // The same exclusions as the load path: check must not
+                // lint (or hard-fail on) files the scan would skip.
+                let (loaded, _) = loader::load_commands_with_summary(
+                    dir,
+                    true,
+                    config.recursive,
+                    config.duplicate_policy,
+                    config.allow_unknown_fields,
+                    cli_args.max_depth.or(config.max_depth),
+                    &exclude_dirs,
+                )?;
+                count += loaded.len();
+                if let Some(allowed_tags) = &config.allowed_tags {
+                    violations.extend(unknown_tag_violations(loaded.values(), allowed_tags));
+                }
+                for def in loaded.values() {
+                    if def.command.trim().is_empty() {
+                        violations.push(format!(
+                            "{:?} in {} has an empty command",
+                            def.key(),
+                            def.source_file.display()
+                        ));
+                    }
+                    if *check_syntax {
+                        if let Some(problem) = syntax_violation(def) {
+                            violations.push(problem);
+                        }
+                    }
+                    for finding in placeholder_findings(def, true) {
+                        eprintln!("Warning: {finding}");
+                    }
+                }
+                if *warn_sudo {
+                    for warning in sudo_warnings(loaded.values()) {
+                        eprintln!("Warning: {warning}");
+                    }
+                }
+            }
+            if !violations.is_empty() {
+                bail!("Problems found:\n{}", violations.join("\n"));
+            }
+            if !cli_args.quiet {
+                eprintln!("OK: {count} commands");
+            }
+        }
+        Some(Action::Doctor) => run_doctor(&config, &scan_dirs),
+        Some(Action::Config(_))
+        | Some(Action::Log { .. })
+        | Some(Action::ImportHistory { .. }) => {
+            unreachable!("handled before loading")
+        }
+        Some(Action::Clip) => {
+            select_and_act(&commands_vec, &cli_args, &config, empty, SelectionAction::Clip)?;
+        }
+        Some(Action::Edit) => {
+            select_and_act(&commands_vec, &cli_args, &config, empty, SelectionAction::Edit)?;
+        }
+        Some(Action::Open) => {
+            select_and_act(&commands_vec, &cli_args, &config, empty, SelectionAction::Open)?;
+        }
+        Some(Action::Run { name, exact, args }) => {
+            let def = find_run_target(&commands_vec, name, *exact)?;
+            let def = with_extra_args(def, args);
+            perform_action(&def, &cli_args, &config, SelectionAction::Run)?;
+        }
+        None => {
+            if cli_args.last {
+                match usage::load_last_run() {
+                    Some(key) => {
+                        if let Some(def) =
+                            commands_vec.iter().find(|def| def.key() == key)
+                        {
+                            perform_action(def, &cli_args, &config, SelectionAction::Run)?;
+                            return Ok(());
+                        }
+                        eprintln!(
+                            "Last command {key:?} no longer exists; opening the picker"
+                        );
+                    }
+                    None => eprintln!("No last command recorded; opening the picker"),
+                }
+            }
+            if cli_args.random {
+                if commands_vec.is_empty() {
+                    eprintln!("{empty}");
+                    return Ok(());
+                }
+                let def = &commands_vec[random_index(commands_vec.len())];
+                perform_action(def, &cli_args, &config, SelectionAction::Run)?;
+                return Ok(());
+            }
+            if cli_args.first {
+                let query = cli_args
+                    .query
+                    .as_deref()
+                    .context("--first requires --query")?;
+                let def = find_first_match(&commands_vec, query)?;
+                perform_action(def, &cli_args, &config, SelectionAction::Run)?;
+                return Ok(());
+            }
+            #[cfg(feature = "tui")]
+            if cli_args.tui {
+                if commands_vec.is_empty() {
+                    eprintln!("{empty}");
+                    return Ok(());
+                }
+                if let Some(def) = tui::pick(&commands_vec)? {
+                    perform_action(&def, &cli_args, &config, SelectionAction::Run)?;
+                }
+                return Ok(());
+            }
+            let action = default_action(&config, &cli_args);
+            select_and_act(&commands_vec, &cli_args, &config, empty, action)?;
+        }
+    }
+    Ok(())
+}
+
+/// The action for a bare `cmdy` invocation: the configured
+/// `default_action`, except that run-flavored flags like `--dry-run` or
+/// `--print-path` always mean "run" regardless of the config — they are
+/// honored inside `run_selection`, which Clip/Edit never reach.
+fn default_action(config: &AppConfig, cli_args: &CliArgs) -> SelectionAction {
+    if cli_args.dry_run
+        || cli_args.print_command
+        || cli_args.print_path
+        || cli_args.print_file_and_line
+        || cli_args.explain
+        || cli_args.edit_before_run
+        || cli_args.exec_template.is_some()
+        || cli_args.watch.is_some()
+    {
+        return SelectionAction::Run;
+    }
+    match config.default_action {
+        config::DefaultAction::Run => SelectionAction::Run,
+        config::DefaultAction::Clip => SelectionAction::Clip,
+        config::DefaultAction::Edit => SelectionAction::Edit,
+    }
+}
+
+/// The `--print-file-and-line` output for a selection: one
+/// machine-parseable `path:line` line.
+fn file_and_line(def: &CommandDef) -> String {
+    format!("{}:{}", def.source_file.display(), def.line)
+}
+
+/// Reads recent shell history and renders it as `[[commands]]` blocks for
+/// the user to curate: printed to stdout, or appended to `imported.toml`
+/// in the commands directory with `--write`.
+fn import_history(last: usize, write: bool, quiet: bool) -> Result<()> {
+    let shell = history::detect_shell();
+    let path = history::get_history_file_path(&shell)?;
+    let contents = std::fs::read_to_string(&path)
+        .with_context(|| format!("Could not read history file {}", path.display()))?;
+    let recent = history::recent_unique_commands(&contents, last);
+    if recent.is_empty() {
+        eprintln!("No history entries found");
+        return Ok(());
+    }
+    let rendered = history_import_toml(&recent);
+    if write {
+        let target = config::get_commands_dir()?.join("imported.toml");
+        if let Some(parent) = target.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("Could not create {}", parent.display()))?;
+        }
+        use std::io::Write as _;
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&target)
+            .with_context(|| format!("Could not open {}", target.display()))?;
+        file.write_all(rendered.as_bytes())
+            .with_context(|| format!("Could not write to {}", target.display()))?;
+        if !quiet {
+            eprintln!("Appended {} commands to {}", recent.len(), target.display());
+        }
+    } else {
+        print!("{rendered}");
+    }
+    Ok(())
+}
+
+/// Renders history entries as snippet tables with blank descriptions for
+/// the user to fill in. Commands go through TOML string escaping, so
+/// quotes and backslashes survive.
+fn history_import_toml(commands: &[String]) -> String {
+    let mut rendered = String::new();
+    for command in commands {
+        rendered.push_str("[[commands]]\n");
+        rendered.push_str("description = \"\"\n");
+        rendered.push_str(&format!(
+            "command = {}\n\n",
+            toml::Value::String(command.clone())
+        ));
+    }
+    rendered
+}
+
+/// Fills in an `--exec` template: `{}` becomes the selected command and
+/// `{file}` its source file.
+fn render_exec_template(template: &str, def: &CommandDef) -> String {
+    template
+        .replace("{file}", &def.source_file.display().to_string())
+        .replace("{}", &def.command)
+}
+
+/// Sorts the commands for the picker and list output, optionally reversing
+/// the result regardless of which sort mode is active.
+fn sort_commands(commands_vec: &mut [CommandDef], sort: SortMode, reverse: bool) {
+    match sort {
+        SortMode::Alphabetical => {
+            commands_vec.sort_by(|a, b| a.description.cmp(&b.description));
+        }
+        SortMode::Frequency => {
+            let counts = usage::load_usage();
+            commands_vec.sort_by(|a, b| {
+                let count_a = counts.get(&a.description).copied().unwrap_or(0);
+                let count_b = counts.get(&b.description).copied().unwrap_or(0);
+                count_b
+                    .cmp(&count_a)
+                    .then_with(|| a.description.cmp(&b.description))
+            });
+        }
+        SortMode::Priority => {
+            commands_vec.sort_by(|a, b| {
+                b.priority
+                    .cmp(&a.priority)
+                    .then_with(|| a.description.cmp(&b.description))
+            });
+        }
+    }
+    if reverse {
+        commands_vec.reverse();
+    }
+}
+
+/// Reads a tag-filter file: one tag per line, with blank lines and `#`
+/// comments skipped. A missing file is an error, since a typo'd path
+/// silently matching everything would be worse.
+fn read_tag_file(path: &Path) -> Result<Vec<String>> {
+    let contents = std::fs::read_to_string(path)
+        .with_context(|| format!("Could not read tag file {}", path.display()))?;
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// Finds the snippet `run <name>` refers to. An exact description or key
+/// match always wins; otherwise a case-insensitive subsequence match
+/// (`dps` matches "Deploy Production Staging") is accepted when it's
+/// unambiguous. Several candidates are listed instead of guessing.
+fn find_run_target<'a>(
+    commands_vec: &'a [CommandDef],
+    name: &str,
+    exact: bool,
+) -> Result<&'a CommandDef> {
+    if let Some(def) = commands_vec
+        .iter()
+        .find(|def| def.description == name || def.key() == name)
+    {
+        return Ok(def);
+    }
+    if exact {
+        bail!("No command named {name:?}");
+    }
+    let candidates: Vec<&CommandDef> = commands_vec
+        .iter()
+        .filter(|def| is_subsequence(name, &def.description))
+        .collect();
+    match candidates.as_slice() {
+        [] => bail!("No command named {name:?}"),
+        [only] => Ok(only),
+        _ => {
+            let listed: Vec<String> = candidates
+                .iter()
+                .map(|def| format!("  {}", def.description))
+                .collect();
+            bail!(
+                "{name:?} is ambiguous; did you mean one of these?\n{}",
+                listed.join("\n")
+            );
+        }
+    }
+}
+
+/// Whether every character of `query` appears in `candidate` in order,
+/// ignoring case: the same notion of matching fzf uses by default.
+fn is_subsequence(query: &str, candidate: &str) -> bool {
+    let mut chars = candidate.chars().flat_map(char::to_lowercase);
+    query
+        .chars()
+        .flat_map(char::to_lowercase)
+        .all(|wanted| chars.any(|c| c == wanted))
+}
+
+/// Merges one directory's (or remote's) worth of loaded commands into the
+/// running map, applying the duplicate policy across sources the same way
+/// the loader applies it within one.
+fn merge_commands(
+    commands: &mut BTreeMap<String, CommandDef>,
+    loaded: BTreeMap<String, CommandDef>,
+    policy: DuplicatePolicy,
+) -> Result<()> {
+    for (key, def) in loaded {
+        if let Some(existing) = commands.get(&key) {
+            match policy {
+                DuplicatePolicy::Error => {
+                    let kind = if def.id.is_some() { "id" } else { "description" };
+                    // A typed error, so --json can classify it.
+                    return Err(loader::LoaderError::Duplicate {
+                        kind,
+                        name: key,
+                        first: existing.source_file.clone(),
+                        second: def.source_file.clone(),
+                    }
+                    .into());
+                }
+                DuplicatePolicy::First => {
+                    eprintln!(
+                        "Note: keeping {key:?} from {}; ignoring the one in {}",
+                        existing.source_file.display(),
+                        def.source_file.display()
+                    );
+                    continue;
+                }
+                DuplicatePolicy::Last => {
+                    eprintln!(
+                        "Note: {key:?} from {} replaces the one in {}",
+                        def.source_file.display(),
+                        existing.source_file.display()
+                    );
+                }
+            }
+        }
+        commands.insert(key, def);
+    }
+    Ok(())
+}
+
+/// Appends extra CLI arguments to a command, shell-quoted, so
+/// `cmdy run deploy -- --dry-run` runs `deploy --dry-run`. The returned
+/// def flows through the normal run path, so `--dry-run` shows the final
+/// command too.
+fn with_extra_args(def: &CommandDef, args: &[String]) -> CommandDef {
+    if args.is_empty() {
+        return def.clone();
+    }
+    let quoted: Vec<String> = args.iter().map(|arg| exec::shell_quote(arg)).collect();
+    CommandDef {
+        command: format!("{} {}", def.command, quoted.join(" ")),
+        ..def.clone()
+    }
+}
+
+/// Whether `tag` matches a `--tag`/`--not-tag` pattern: an exact match,
+/// or a prefix match when the pattern ends in `*` (`experimental*`
+/// covers both `experimental` and `experimental-ui`). Both flags share
+/// this matcher so include and exclude stay symmetric.
+fn tag_matches(pattern: &str, tag: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => tag.starts_with(prefix),
+        None => pattern == tag,
+    }
+}
+
+/// Whether any of the command's tags starts with `prefix`,
+/// case-insensitively. Backs `--tag-prefix`, which matches tag families
+/// like `prod`, `prod-eu`, and `prod-us` in one flag.
+fn has_tag_prefix(def: &CommandDef, prefix: &str) -> bool {
+    let prefix = prefix.to_lowercase();
+    def.tags
+        .iter()
+        .any(|tag| tag.to_lowercase().starts_with(&prefix))
+}
+
+/// Floats anything tagged with the favorite tag above the rest, keeping
+/// the chosen sort order within each half. Runs after `sort_commands` so
+/// favorites lead regardless of sort mode.
+fn float_favorites(commands_vec: &mut [CommandDef], favorite_tag: &str) {
+    commands_vec.sort_by_key(|def| !def.tags.iter().any(|tag| tag == favorite_tag));
+}
+
+/// Applies `--limit`: keeps only the first N commands after filtering and
+/// sorting, so `--sort frequency --limit 20` means "my top 20".
+fn limit_commands(commands_vec: &mut Vec<CommandDef>, limit: Option<usize>) {
+    if let Some(limit) = limit {
+        commands_vec.truncate(limit);
+    }
+}
+
+/// Lints a snippet's `{{placeholder}}` tokens against its `defaults`.
+/// A default with no matching placeholder is always reported (it's a typo
+/// on one side); placeholders that will prompt interactively are only
+/// reported when `include_prompts` is set, since prompting is a feature.
+fn placeholder_findings(def: &CommandDef, include_prompts: bool) -> Vec<String> {
+    let names = exec::placeholder_names(&def.command);
+    let mut findings = Vec::new();
+    if include_prompts {
+        for name in &names {
+            if !def.defaults.contains_key(name) {
+                findings.push(format!(
+                    "{:?} will prompt for {{{{{name}}}}} ({})",
+                    def.description,
+                    def.source_file.display()
+                ));
+            }
+        }
+    }
+    for key in def.defaults.keys() {
+        if !names.iter().any(|name| name == key) {
+            findings.push(format!(
+                "{:?} has a default {key:?} that matches no placeholder ({})",
+                def.description,
+                def.source_file.display()
+            ));
+        }
+    }
+    findings
+}
+
+/// `check --check-syntax`: parses one command with `sh -n` (parse only,
+/// never execute) and describes the failure, or `None` when the shell
+/// accepts it. `{{placeholders}}` are left in place; they're plain words
+/// to the parser.
+fn syntax_violation(def: &CommandDef) -> Option<String> {
+    let output = Command::new("sh")
+        .args(["-n", "-c", &def.command])
+        .output()
+        .ok()?;
+    if output.status.success() {
+        return None;
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    Some(format!(
+        "{:?} in {} does not parse: {}",
+        def.key(),
+        def.source_file.display(),
+        stderr.trim().lines().last().unwrap_or("shell syntax error")
+    ))
+}
+
+/// `check --warn-sudo`: lists snippets that invoke `sudo` without a
+/// `confirm` prompt, nudging toward safer configuration. These are
+/// warnings, not violations: a confirmed sudo command is a fine snippet.
+fn sudo_warnings<'a>(
+    commands: impl Iterator<Item = &'a CommandDef>,
+) -> Vec<String> {
+    commands
+        .filter(|def| !def.confirm.is_required() && invokes_sudo(&def.command))
+        .map(|def| {
+            format!(
+                "{:?} in {} uses sudo without confirm; consider `confirm = true` or a warning tag",
+                def.key(),
+                def.source_file.display()
+            )
+        })
+        .collect()
+}
+
+/// Whether a command mentions `sudo` as a word of its own. This is a
+/// word-boundary heuristic, not a parse: substrings of larger words like
+/// `visudo` don't trip it, but a quoted `sudo` (e.g. in an echoed
+/// string) still does — for a warning lint, the occasional false
+/// positive beats missing a real invocation.
+fn invokes_sudo(command: &str) -> bool {
+    command
+        .split(|c: char| c.is_whitespace() || ";|&()".contains(c))
+        .any(|word| word == "sudo")
+}
+
+/// Lists snippets using tags outside the configured allowlist, one line
+/// per offending tag, naming the source file so typos are easy to fix.
+fn unknown_tag_violations<'a>(
+    commands: impl Iterator<Item = &'a CommandDef>,
+    allowed_tags: &[String],
+) -> Vec<String> {
+    let mut violations = Vec::new();
+    for def in commands {
+        for tag in &def.tags {
+            if !allowed_tags.contains(tag) {
+                violations.push(format!(
+                    "  {:?} uses unknown tag \"{}\" ({})",
+                    def.description,
+                    tag,
+                    def.source_file.display()
+                ));
+            }
+        }
+    }
+    violations
+}
+
+/// A uniformly random index below `len`, for `--random`. Uses the std
+/// hasher's per-instance OS seeding rather than pulling in an RNG crate
+/// for one index.
+fn random_index(len: usize) -> usize {
+    use std::hash::{BuildHasher, Hasher};
+    let hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    (hasher.finish() as usize) % len
+}
+
+/// Resolves `--first`: the single best match for a query, without the
+/// interactive picker. An exact description match wins; otherwise the query
+/// must be a substring of exactly one description.
+fn find_first_match<'a>(commands_vec: &'a [CommandDef], query: &str) -> Result<&'a CommandDef> {
+    if let Some(def) = commands_vec.iter().find(|def| def.description == query) {
+        return Ok(def);
+    }
+    let needle = query.to_lowercase();
+    let matches: Vec<&CommandDef> = commands_vec
+        .iter()
+        .filter(|def| def.description.to_lowercase().contains(&needle))
+        .collect();
+    match matches.len() {
+        0 => bail!("No command matches {query:?}"),
+        1 => Ok(matches[0]),
+        n => bail!(
+            "{query:?} is ambiguous ({n} matches); refine the query or use the picker"
+        ),
+    }
+}
+
+/// Explains an empty command list: a missing directory, a directory with
+/// no snippet files, and files that all failed to parse are different
+/// problems with different fixes.
+fn empty_message(any_scan_dir: bool, summary: &loader::LoadSummary) -> &'static str {
+    if !any_scan_dir {
+        "No snippet directory found (create one or pass --dir)"
+    } else if summary.files_seen == 0 {
+        "No snippet files (*.toml) found"
+    } else if summary.files_parsed == 0 {
+        "No snippet files could be parsed (see the warnings above)"
+    } else {
+        "No command snippets found"
+    }
+}
+
+/// Runs the picker over the filtered commands, reporting when there's
+/// nothing to pick from.
+fn pick<'a>(
+    commands_vec: &'a [CommandDef],
+    cli_args: &CliArgs,
+    config: &AppConfig,
+    empty: &str,
+) -> Result<Option<&'a CommandDef>> {
+    if commands_vec.is_empty() {
+        eprintln!("{empty}");
+        return Ok(None);
+    }
+    // Spawning a picker against a pipe just fails confusingly; say what
+    // the non-interactive alternatives are instead.
+    {
+        use std::io::IsTerminal;
+        if !std::io::stdin().is_terminal() {
+            bail!(
+                "stdin is not a terminal; use --query with --first, `list`, \
+                 or `run <name>` instead of the interactive picker"
+            );
+        }
+    }
+    // An explicit --query always wins; the remembered one only fills the
+    // gap when the user asked for that behavior (and isn't clearing it).
+    let remembered = if cli_args.query.is_none() && config.remember_query && !cli_args.forget {
+        usage::load_last_query()
+    } else {
+        None
+    };
+    ui::choose_command(
+        commands_vec,
+        config,
+        cli_args.query.as_deref().or(remembered.as_deref()),
+        &cli_args.tags,
+        &cli_args.not_tags,
+    )
+}
+
+/// One list/search line: the `--output-template` rendering when given,
+/// otherwise the classic description-plus-tags layout.
+fn output_line(def: &CommandDef, cli_args: &CliArgs) -> String {
+    match &cli_args.output_template {
+        Some(template) => apply_output_template(template, def),
+        None => list_line(def),
+    }
+}
+
+/// Fills in an `--output-template`. Escapes are expanded before tokens, so
+/// a command containing a literal backslash sequence comes through intact.
+fn apply_output_template(template: &str, def: &CommandDef) -> String {
+    let tags: Vec<String> = def.tags.iter().map(|tag| format!("#{tag}")).collect();
+    template
+        .replace("\\t", "\t")
+        .replace("\\n", "\n")
+        .replace("{description}", &def.description)
+        .replace("{command}", &def.command)
+        .replace("{tags}", &tags.join(" "))
+        .replace("{file}", &def.source_file.display().to_string())
+}
+
+/// Renders `list --format table`: aligned description, tags, and file
+/// columns. The first two columns size to their widest entry; the file
+/// column takes whatever terminal width is left and is truncated with an
+/// ellipsis when it doesn't fit.
+fn list_table(commands: &[CommandDef], width: usize) -> String {
+    if commands.is_empty() {
+        return "No commands to list\n".to_string();
+    }
+    let rows: Vec<(String, String, String)> = commands
+        .iter()
+        .map(|def| {
+            let description = if def.enabled {
+                def.description.clone()
+            } else {
+                format!("{} (disabled)", def.description)
+            };
+            let tags: Vec<String> =
+                def.tags.iter().map(|tag| format!("#{tag}")).collect();
+            (description, tags.join(" "), def.source_file.display().to_string())
+        })
+        .collect();
+    let description_width = rows
+        .iter()
+        .map(|(description, _, _)| description.chars().count())
+        .max()
+        .unwrap_or(0);
+    let tags_width = rows
+        .iter()
+        .map(|(_, tags, _)| tags.chars().count())
+        .max()
+        .unwrap_or(0);
+    let file_width = width.saturating_sub(description_width + tags_width + 4).max(8);
+    let mut table = String::new();
+    for (description, tags, file) in rows {
+        let file = truncate_column(&file, file_width);
+        let line = format!(
+            "{description:<description_width$}  {tags:<tags_width$}  {file}"
+        );
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Truncates one table cell to `width` characters, ending in an ellipsis.
+fn truncate_column(cell: &str, width: usize) -> String {
+    if cell.chars().count() <= width {
+        return cell.to_string();
+    }
+    let mut truncated: String = cell.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// The terminal width from `$COLUMNS`, defaulting to 80 columns.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|width| *width > 0)
+        .unwrap_or(80)
+}
+
+fn list_line(def: &CommandDef) -> String {
+    if def.tags.is_empty() {
+        def.description.clone()
+    } else {
+        let tags: Vec<String> = def.tags.iter().map(|tag| format!("#{tag}")).collect();
+        format!("{} {}", def.description, tags.join(" "))
+    }
+}
+
+/// What to do with a snippet once one has been selected. Selection itself
+/// (tag filters, query, the picker) is identical for every action; only
+/// this final step differs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SelectionAction {
+    /// Execute it (or dry-run/print it, per the flags).
+    Run,
+    /// Copy the expanded command to the clipboard.
+    Clip,
+    /// Open its source file in the editor.
+    Edit,
+    /// Reveal its source file in the OS file manager.
+    Open,
+}
+
+/// Runs the picker and applies `action` to the choice; a dismissed picker
+/// is a clean no-op.
+fn select_and_act(
+    commands_vec: &[CommandDef],
+    cli_args: &CliArgs,
+    config: &AppConfig,
+    empty: &str,
+    action: SelectionAction,
+) -> Result<()> {
+    let Some(def) = pick(commands_vec, cli_args, config, empty)? else {
+        return Ok(());
+    };
+    perform_action(def, cli_args, config, action)
+}
+
+/// The terminal step shared by every selection path. Clip goes through the
+/// same placeholder expansion and usage tracking as a real run, so a
+/// copied command is the command that would have executed.
+fn perform_action(
+    def: &CommandDef,
+    cli_args: &CliArgs,
+    config: &AppConfig,
+    action: SelectionAction,
+) -> Result<()> {
+    match action {
+        SelectionAction::Run => run_selection(def, cli_args, config),
+        SelectionAction::Clip => {
+            let command = exec::substitute_placeholders(&def.command, &def.defaults)?;
+            clipboard::copy_to_clipboard(&command)?;
+            usage::record_usage(&def.description);
+            if !cli_args.quiet && !config.quiet_clip {
+                eprintln!("Copied command to clipboard");
+            }
+            Ok(())
+        }
+        SelectionAction::Edit => open_in_editor(config, &def.source_file),
+        SelectionAction::Open => reveal_in_file_manager(&def.source_file),
+    }
+}
+
+/// Reveals `file` in the OS file manager: `open -R` on macOS,
+/// `explorer /select,` on Windows, and `xdg-open` on the parent directory
+/// everywhere else (Linux file managers have no portable select verb).
+fn reveal_in_file_manager(file: &Path) -> Result<()> {
+    #[cfg(target_os = "macos")]
+    let status = Command::new("open").arg("-R").arg(file).status();
+    #[cfg(windows)]
+    let status = Command::new("explorer")
+        .arg(format!("/select,{}", file.display()))
+        .status();
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let status = Command::new("xdg-open")
+        .arg(file.parent().unwrap_or(Path::new(".")))
+        .status();
+    let status =
+        status.context("Could not run the file manager (is xdg-open installed?)")?;
+    if !status.success() {
+        bail!("File manager exited with {status}");
+    }
+    Ok(())
+}
+
+/// Parses the repeatable `--var KEY=VALUE` flags into a map.
+fn parse_vars(raw: &[String]) -> Result<BTreeMap<String, String>> {
+    let mut vars = BTreeMap::new();
+    for entry in raw {
+        let Some((key, value)) = entry.split_once('=') else {
+            bail!("--var takes KEY=VALUE, got {entry:?}");
+        };
+        vars.insert(key.to_string(), value.to_string());
+    }
+    Ok(vars)
+}
+
+/// Dispatches a selected command to dry-run, print, or real execution.
+fn run_selection(def: &CommandDef, cli_args: &CliArgs, config: &AppConfig) -> Result<()> {
+    let vars = parse_vars(&cli_args.vars)?;
+    if cli_args.explain {
+        explain_selection(def, config, &vars);
+        return Ok(());
+    }
+    if cli_args.dry_run {
+        let command =
+            exec::substitute_known_placeholders(&def.command, &def.defaults, &vars);
+        match cli_args.format {
+            Some(OutputFormat::Json) => println!("{}", dry_run_json(def, &command)?),
+            // --format table only applies to list; a dry run with it falls
+            // through to the usual human output.
+            _ if cli_args.json => println!("{}", dry_run_json(def, &command)?),
+            // The human-readable forms go to stderr, keeping stdout clean
+            // for `$(...)` capture; only the JSON forms are machine output.
+            _ if ui::stdout_supports_color() => {
+                eprintln!("{}", ui::dim("Would execute:"));
+                eprintln!("  {}", ui::highlight(&command));
+                eprintln!("{}", ui::dim("From file:"));
+                eprintln!("  {}", def.source_file.display());
+                if let Some(log_file) = &def.log_file {
+                    eprintln!("{}", ui::dim("Logging to:"));
+                    eprintln!("  {}", config::expand_path(log_file).display());
+                }
+                if config.load_dotenv {
+                    for (key, value) in exec::dotenv_vars(&def.source_file) {
+                        eprintln!("{}", ui::dim(&format!("Env from .env: {key}={value}")));
+                    }
+                }
+            }
+            _ => {
+                eprintln!("Would execute:");
+                eprintln!("  {command}");
+                eprintln!("From file:");
+                eprintln!("  {}", def.source_file.display());
+                if let Some(log_file) = &def.log_file {
+                    eprintln!("Logging to:");
+                    eprintln!("  {}", config::expand_path(log_file).display());
+                }
+                if config.load_dotenv {
+                    for (key, value) in exec::dotenv_vars(&def.source_file) {
+                        eprintln!("Env from .env: {key}={value}");
+                    }
+                }
+            }
+        }
+        // --interactive bridges the gap to a real run: having seen the
+        // command, the user can accept it without re-invoking. Declining
+        // (or EOF) is a clean exit, exactly like a plain dry run.
+        if !cli_args.interactive || !exec::confirm("Run it now?")? {
+            return Ok(());
+        }
+    }
+    if cli_args.print_command {
+        println!("{}", def.command);
+        return Ok(());
+    }
+    if cli_args.print_path {
+        println!("{}", def.source_file.display());
+        return Ok(());
+    }
+    if cli_args.print_file_and_line {
+        println!("{}", file_and_line(def));
+        return Ok(());
+    }
+    // A snippet with alternatives defers the final choice of command to a
+    // quick secondary pick through the same filter program.
+    let chosen_alternative;
+    let def = if def.alternatives.is_empty() {
+        def
+    } else {
+        let Some(command) = ui::choose_string(&def.alternatives, config)? else {
+            return Ok(()); // dismissed the pick; a clean abort
+        };
+        chosen_alternative = CommandDef {
+            command,
+            ..def.clone()
+        };
+        &chosen_alternative
+    };
+    let edited;
+    let def = if cli_args.edit_before_run {
+        let Some(command) = edit_command_in_editor(config, &def.command)? else {
+            eprintln!("Nothing to run after editing; aborting");
+            return Ok(());
+        };
+        edited = CommandDef {
+            command,
+            ..def.clone()
+        };
+        &edited
+    } else {
+        def
+    };
+    if let Some(template) = &cli_args.exec_template {
+        let command = render_exec_template(template, def);
+        let status = exec::run_shell(&command)?;
+        if !status.success() {
+            bail!("Command failed with status {status}");
+        }
+        return Ok(());
+    }
+    if let Some(interval) = &cli_args.watch {
+        return watch_selection(def, cli_args, config, &vars, parse_interval(interval)?);
+    }
+    run_pre_exec_hook(config, def)?;
+    let force_confirm = cli_args.confirm || config.confirm_all;
+    let Some(outcome) =
+        exec::execute_command(
+            def,
+            force_confirm,
+            config.login_shell,
+            &vars,
+            config.load_dotenv,
+            config.log_executions,
+        )?
+    else {
+        return Ok(()); // declined the confirmation; not an error
+    };
+    if let Some(hook) = &config.post_exec {
+        let hook_command = render_hook_template(hook, def, &outcome);
+        match exec::run_shell(&hook_command) {
+            Ok(status) if !status.success() => {
+                eprintln!("Warning: post_exec hook exited with {status}");
+            }
+            Err(err) => eprintln!("Warning: could not run post_exec hook: {err}"),
+            Ok(_) => {}
+        }
+    }
+    if !def.succeeded(outcome.status) {
+        bail!("Command failed with status {}", outcome.status);
+    }
+    if cli_args.history || config.overwrite_shell_command {
+        match history::append_to_shell_history(&outcome.command) {
+            Ok(shell) if !cli_args.quiet => {
+                eprintln!("Added to {} history", shell.name());
+            }
+            Ok(_) => {}
+            Err(err) => eprintln!("Warning: could not write shell history: {err}"),
+        }
+    }
+    Ok(())
+}
+
+/// `--explain`: a richer dry run that breaks the selection down — the
+/// resolved command, env overrides, cwd, placeholders that would prompt,
+/// and the binaries the command needs with their PATH status. Human
+/// output, so it goes to stderr like the dry-run preview.
+fn explain_selection(def: &CommandDef, config: &AppConfig, vars: &BTreeMap<String, String>) {
+    let command = exec::substitute_known_placeholders(&def.command, &def.defaults, vars);
+    eprintln!("Command:");
+    eprintln!("  {command}");
+    eprintln!("From file:");
+    eprintln!("  {}", def.source_file.display());
+    if let Some(cwd) = &def.cwd {
+        eprintln!("Working directory:");
+        eprintln!("  {}", config::expand_path(cwd).display());
+    }
+    let mut env: Vec<(String, String)> = Vec::new();
+    if config.load_dotenv {
+        env.extend(exec::dotenv_vars(&def.source_file));
+    }
+    env.extend(def.env.iter().map(|(key, value)| (key.clone(), value.clone())));
+    if !env.is_empty() {
+        eprintln!("Environment:");
+        for (key, value) in env {
+            eprintln!("  {key}={value}");
+        }
+    }
+    let prompts: Vec<String> = exec::placeholder_names(&def.command)
+        .into_iter()
+        .filter(|name| !vars.contains_key(name) && !def.defaults.contains_key(name))
+        .collect();
+    if !prompts.is_empty() {
+        eprintln!("Will prompt for:");
+        for name in prompts {
+            eprintln!("  {{{{{name}}}}}");
+        }
+    }
+    let binaries = required_binaries(&command);
+    if !binaries.is_empty() {
+        eprintln!("Requires:");
+        for binary in binaries {
+            let status = if ui::binary_on_path(&binary) {
+                "found"
+            } else {
+                "not on PATH"
+            };
+            eprintln!("  {binary} ({status})");
+        }
+    }
+}
+
+/// The binaries a shell command appears to invoke: the first word of each
+/// pipeline segment, skipping env assignments, shell keywords, and
+/// anything that still contains unresolved syntax. A heuristic, but good
+/// enough to warn that `kubectl` isn't installed before the run fails.
+fn required_binaries(command: &str) -> Vec<String> {
+    const SHELL_WORDS: &[&str] = &[
+        "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case",
+        "esac", "time", "exec", "env", "sudo", "nohup",
+    ];
+    let mut binaries = Vec::new();
+    for segment in command.split(['|', ';', '\n']).flat_map(|part| part.split("&&")) {
+        let mut words = segment.split_whitespace().skip_while(|word| {
+            word.contains('=') || SHELL_WORDS.contains(word)
+        });
+        let Some(word) = words.next() else { continue };
+        if word.starts_with(['$', '{', '(', '"', '\'']) {
+            continue;
+        }
+        let word = word.to_string();
+        if !binaries.contains(&word) {
+            binaries.push(word);
+        }
+    }
+    binaries
+}
+
+/// The `--watch` loop: clears the screen and re-runs the selection on the
+/// given interval, like watch(1). Each run's exit status is reported;
+/// `--watch-count` bounds the number of iterations, and Ctrl-C (or a
+/// declined confirmation) ends the loop.
+fn watch_selection(
+    def: &CommandDef,
+    cli_args: &CliArgs,
+    config: &AppConfig,
+    vars: &BTreeMap<String, String>,
+    interval: Duration,
+) -> Result<()> {
+    let force_confirm = cli_args.confirm || config.confirm_all;
+    let mut runs = 0u64;
+    loop {
+        // The same clear sequence watch(1) uses: wipe and home the cursor.
+        print!("\x1b[2J\x1b[H");
+        use std::io::Write as _;
+        let _ = std::io::stdout().flush();
+        let Some(outcome) = exec::execute_command(
+            def,
+            force_confirm,
+            config.login_shell,
+            vars,
+            config.load_dotenv,
+            config.log_executions,
+        )?
+        else {
+            return Ok(()); // declined the confirmation
+        };
+        runs += 1;
+        eprintln!("[watch {runs}] exited with {}", outcome.status);
+        if cli_args.watch_count.is_some_and(|count| runs >= count) {
+            return Ok(());
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+/// Parses a watch interval like `5s`, `500ms`, `2m`, or `1h`; a bare
+/// number means seconds.
+fn parse_interval(raw: &str) -> Result<Duration> {
+    let raw = raw.trim();
+    let split = raw
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(raw.len());
+    let (value, unit) = raw.split_at(split);
+    let value: u64 = value
+        .parse()
+        .with_context(|| format!("Bad interval {raw:?}"))?;
+    match unit {
+        "ms" => Ok(Duration::from_millis(value)),
+        "" | "s" => Ok(Duration::from_secs(value)),
+        "m" => Ok(Duration::from_secs(value * 60)),
+        "h" => Ok(Duration::from_secs(value * 3600)),
+        _ => bail!("Bad interval unit {unit:?} in {raw:?} (use ms, s, m, or h)"),
+    }
+}
+
+/// Runs the configured `pre_exec` hook, if any. The hook's output goes
+/// straight to the terminal; a non-zero exit blocks the snippet from
+/// running, which makes custom policy checks possible.
+fn run_pre_exec_hook(config: &AppConfig, def: &CommandDef) -> Result<()> {
+    let Some(hook) = &config.pre_exec else {
+        return Ok(());
+    };
+    let hook_command = hook
+        .replace("{description}", &def.description)
+        .replace("{command}", &def.command);
+    let status = exec::run_shell(&hook_command)?;
+    if !status.success() {
+        bail!("pre_exec hook blocked execution ({status})");
+    }
+    Ok(())
+}
+
+/// Fills in a pre/post-execution hook template with the command's info.
+fn render_hook_template(
+    template: &str,
+    def: &CommandDef,
+    outcome: &exec::ExecOutcome,
+) -> String {
+    let status = outcome
+        .status
+        .code()
+        .map(|code| code.to_string())
+        .unwrap_or_else(|| "-1".to_string());
+    template
+        .replace("{description}", &def.description)
+        .replace("{command}", &outcome.command)
+        .replace("{status}", &status)
+}
+
+/// Serializes commands for the `--json` list/search output.
+fn commands_json<'a>(commands: impl Iterator<Item = &'a CommandDef>) -> Result<String> {
+    let listed: Vec<serde_json::Value> = commands
+        .map(|def| {
+            serde_json::json!({
+                "description": def.description,
+                "command": def.command,
+                "tags": def.tags,
+                "enabled": def.enabled,
+                "source_file": def.source_file,
+            })
+        })
+        .collect();
+    Ok(serde_json::to_string(&listed)?)
+}
+
+/// The machine-readable form of a dry run, for editor plugins and other
+/// tooling that wants to preview what cmdy would do.
+fn dry_run_json(def: &CommandDef, command: &str) -> Result<String> {
+    let payload = serde_json::json!({
+        "command": command,
+        "description": def.description,
+        "source_file": def.source_file,
+        "tags": def.tags,
+    });
+    Ok(serde_json::to_string_pretty(&payload)?)
+}
+
+/// Opens `file` in the user's editor and waits for it to exit.
+///
+/// Precedence: `editor` from cmdy.toml, then `$EDITOR`, then `vi`. The
+/// editor string is split on whitespace like `filter_command`, so editors
+/// that need arguments (`code --wait`) work.
+fn open_in_editor(config: &AppConfig, file: &Path) -> Result<()> {
+    let editor = resolve_editor(config);
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else {
+        bail!("Editor command is empty");
+    };
+    let status = Command::new(program)
+        .args(parts)
+        .arg(file)
+        .status()
+        .with_context(|| format!("Could not run editor {program:?}"))?;
+    if !status.success() {
+        bail!("Editor exited with {status}");
+    }
+    Ok(())
+}
+
+/// `--edit-before-run`: writes the command to a temp file, opens it in
+/// the user's editor, and returns what was saved. `None` means there's
+/// nothing to run: the saved file was empty (or whitespace). The temp
+/// file is removed either way.
+fn edit_command_in_editor(config: &AppConfig, command: &str) -> Result<Option<String>> {
+    let path = env::temp_dir().join(format!("cmdy-edit-{}.sh", std::process::id()));
+    std::fs::write(&path, command)
+        .with_context(|| format!("Could not write {}", path.display()))?;
+    let edit_result = open_in_editor(config, &path);
+    let edited = std::fs::read_to_string(&path).unwrap_or_default();
+    let _ = std::fs::remove_file(&path);
+    edit_result?;
+    let edited = edited.trim_end();
+    if edited.trim().is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(edited.to_string()))
+}
+
+/// The editor command string: config.editor > $EDITOR > vi.
+fn resolve_editor(config: &AppConfig) -> String {
+    config
+        .editor
+        .clone()
+        .or_else(|| env::var("EDITOR").ok().filter(|e| !e.is_empty()))
+        .unwrap_or_else(|| "vi".to_string())
+}
+
+/// `cmdy log`: prints the most recent entries from cmdy's own execution
+/// history (populated when `log_executions` is on).
+fn show_log(last: usize) -> Result<()> {
+    let records = usage::load_history(last);
+    if records.is_empty() {
+        eprintln!("No history yet (set `log_executions = true` in cmdy.toml)");
+        return Ok(());
+    }
+    for record in records {
+        let status = match record.status {
+            Some(code) => code.to_string(),
+            None => "signal".to_string(),
+        };
+        println!(
+            "{}\t[{}]\t{}\t{}",
+            record.timestamp, status, record.description, record.command
+        );
+    }
+    Ok(())
+}
+
+/// `cmdy config check`: parses cmdy.toml strictly and reports anything
+/// that would silently degrade a normal run. Exits non-zero on problems,
+/// unlike the lenient startup path.
+fn config_check(quiet: bool) -> Result<()> {
+    let config = config::load_app_config_strict()?;
+    let problems = config_problems(&config);
+    if !problems.is_empty() {
+        bail!("Config problems:\n{}", problems.join("\n"));
+    }
+    if !quiet {
+        eprintln!("OK: config is valid");
+    }
+    Ok(())
+}
+
+/// The fixable problems in a parsed config: configured directories that
+/// don't exist and a filter command whose binary isn't on PATH.
+fn config_problems(config: &AppConfig) -> Vec<String> {
+    let mut problems = Vec::new();
+    for dir in &config.directories {
+        let expanded = config::expand_path(dir);
+        if !expanded.is_dir() {
+            problems.push(format!("directory {} does not exist", expanded.display()));
+        }
+    }
+    let resolved = ui::resolve_filter_command(&config.filter_command);
+    let filter = resolved.split_whitespace().next().unwrap_or("");
+    if !ui::binary_on_path(filter) {
+        problems.push(format!("filter command {filter:?} is not on PATH"));
+    }
+    problems
+}
+
+/// `--dump-config`: the effective configuration — file values, dir
+/// overrides, and the resolved filter command — rendered as TOML. A
+/// debugging aid that pairs with `doctor`; the output parses back into
+/// the same config.
+fn dump_config(config: &AppConfig) -> Result<String> {
+    let mut effective = config.clone();
+    effective.filter_command = ui::resolve_filter_command(&config.filter_command);
+    toml::to_string_pretty(&effective).context("Could not serialize the config")
+}
+
+fn run_doctor(config: &AppConfig, scan_dirs: &[PathBuf]) {
+    match config::get_config_file_path() {
+        Ok(path) if path.exists() => println!("Config file: {}", path.display()),
+        Ok(path) => println!("Config file: {} (missing, using defaults)", path.display()),
+        Err(err) => println!("Config file: unavailable ({err})"),
+    }
+    for dir in scan_dirs {
+        if dir.is_dir() {
+            println!("Scan directory: {}", dir.display());
+        } else {
+            println!("Scan directory: {} (missing)", dir.display());
+        }
+    }
+    let resolved = ui::resolve_filter_command(&config.filter_command);
+    let filter = resolved.split_whitespace().next().unwrap_or("");
+    let auto = if config.filter_command == "auto" {
+        "auto -> "
+    } else {
+        ""
+    };
+    if ui::binary_on_path(filter) {
+        println!("Filter command: {auto}{filter}");
+    } else {
+        println!("Filter command: {auto}{filter} (not found on PATH)");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args_from(args: &[&str]) -> CliArgs {
+        CliArgs::parse_from(std::iter::once(&"cmdy").chain(args))
+    }
+
+    #[test]
+    fn config_problems_flags_a_missing_directory() {
+        let config = AppConfig {
+            directories: vec![PathBuf::from("/no/such/dir/anywhere")],
+            filter_command: "head -n1".to_string(),
+            ..AppConfig::default()
+        };
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("/no/such/dir/anywhere"));
+    }
+
+    #[test]
+    fn config_problems_flags_a_missing_filter_binary() {
+        let config = AppConfig {
+            filter_command: "definitely-not-a-real-binary --flag".to_string(),
+            ..AppConfig::default()
+        };
+        let problems = config_problems(&config);
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].contains("definitely-not-a-real-binary"));
+    }
+
+    #[test]
+    fn dir_flag_suppresses_configured_directories() {
+        let scratch = tempfile::tempdir().unwrap();
+        let primary = scratch.path().join("primary");
+        let other = scratch.path().join("other");
+        std::fs::create_dir_all(&primary).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        let cli_args = args_from(&["--dir", primary.to_str().unwrap()]);
+        let mut config = AppConfig::default();
+        config.directories.push(other);
+        let dirs = get_scan_dirs(&cli_args, &config).unwrap();
+        assert_eq!(dirs, vec![primary.canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn multiple_dir_flags_are_all_scanned() {
+        let scratch = tempfile::tempdir().unwrap();
+        let first = scratch.path().join("first");
+        let second = scratch.path().join("second");
+        std::fs::create_dir_all(&first).unwrap();
+        std::fs::create_dir_all(&second).unwrap();
+        let cli_args = args_from(&[
+            "--dir",
+            first.to_str().unwrap(),
+            "--dir",
+            second.to_str().unwrap(),
+        ]);
+        let dirs = get_scan_dirs(&cli_args, &AppConfig::default()).unwrap();
+        assert_eq!(
+            dirs,
+            vec![
+                first.canonicalize().unwrap(),
+                second.canonicalize().unwrap()
+            ]
+        );
+    }
+
+    #[test]
+    fn configured_directories_follow_the_default() {
+        let scratch = tempfile::tempdir().unwrap();
+        let extra = scratch.path().join("extra");
+        std::fs::create_dir_all(&extra).unwrap();
+        let cli_args = args_from(&[]);
+        let mut config = AppConfig::default();
+        config.directories.push(extra.clone());
+        let dirs = get_scan_dirs(&cli_args, &config).unwrap();
+        assert_eq!(dirs.last(), Some(&extra.canonicalize().unwrap()));
+    }
+
+    #[test]
+    fn differently_spelled_paths_dedupe_to_one_directory() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path().join("snippets");
+        std::fs::create_dir_all(&dir).unwrap();
+        let dotted = dir.join(".");
+        let cli_args = args_from(&[]);
+        let config = AppConfig {
+            directories: vec![dir.clone(), dotted],
+            ..AppConfig::default()
+        };
+        let dirs = get_scan_dirs(&cli_args, &config).unwrap();
+        let expected = dir.canonicalize().unwrap();
+        assert_eq!(
+            dirs.iter().filter(|d| **d == expected).count(),
+            1,
+            "the directory should only be scanned once: {dirs:?}"
+        );
+    }
+
+    #[test]
+    fn excluded_directories_never_make_the_scan_list() {
+        let keep = tempfile::tempdir().unwrap();
+        let skip = tempfile::tempdir().unwrap();
+        let cli_args = args_from(&[
+            "--dir",
+            keep.path().to_str().unwrap(),
+            "--dir",
+            skip.path().to_str().unwrap(),
+        ]);
+        let config = AppConfig {
+            exclude_directories: vec![skip.path().to_path_buf()],
+            ..AppConfig::default()
+        };
+        let dirs = get_scan_dirs(&cli_args, &config).unwrap();
+        assert_eq!(dirs, vec![keep.path().canonicalize().unwrap()]);
+    }
+
+    #[test]
+    fn missing_directories_are_skipped() {
+        let cli_args = args_from(&["--dir", "/no/such/dir/anywhere"]);
+        let config = AppConfig::default();
+        assert!(get_scan_dirs(&cli_args, &config).unwrap().is_empty());
+    }
+
+    #[test]
+    fn json_list_output_is_an_array_of_objects() {
+        let commands = [def_named("a"), def_named("b")];
+        let rendered = commands_json(commands.iter()).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed.as_array().unwrap().len(), 2);
+        assert_eq!(parsed[0]["description"], "a");
+        assert_eq!(parsed[0]["command"], "true");
+    }
+
+    #[test]
+    fn dry_run_json_has_the_documented_fields() {
+        let def = CommandDef {
+            description: "Show git status".to_string(),
+            command: "git status".to_string(),
+            id: None,
+            tags: vec!["git".to_string()],
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: PathBuf::from("/tmp/git.toml"),
+            line: 1,
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&dry_run_json(&def, &def.command).unwrap()).unwrap();
+        assert_eq!(json["command"], "git status");
+        assert_eq!(json["description"], "Show git status");
+        assert_eq!(json["source_file"], "/tmp/git.toml");
+        assert_eq!(json["tags"][0], "git");
+    }
+
+    #[test]
+    fn history_import_renders_parseable_toml() {
+        let commands = vec![
+            "echo \"quoted\"".to_string(),
+            "git status".to_string(),
+        ];
+        let rendered = history_import_toml(&commands);
+        let parsed: toml::Value = toml::from_str(&rendered).unwrap();
+        let blocks = parsed["commands"].as_array().unwrap();
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0]["command"].as_str(), Some("echo \"quoted\""));
+        assert_eq!(blocks[0]["description"].as_str(), Some(""));
+    }
+
+    #[test]
+    fn output_template_renders_tsv() {
+        let mut def = def_named("Deploy");
+        def.tags = vec!["work".to_string()];
+        let rendered =
+            apply_output_template("{description}\\t{command}\\t{tags}\\t{file}", &def);
+        assert_eq!(rendered, "Deploy\ttrue\t#work\t/tmp/test.toml");
+    }
+
+    #[test]
+    fn config_editor_beats_the_environment() {
+        let config = AppConfig {
+            editor: Some("code --wait".to_string()),
+            ..AppConfig::default()
+        };
+        assert_eq!(resolve_editor(&config), "code --wait");
+    }
+
+    fn def_named(description: &str) -> CommandDef {
+        CommandDef {
+            description: description.to_string(),
+            command: "true".to_string(),
+            id: None,
+            tags: Vec::new(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn sudo_without_confirm_is_listed_in_the_warnings() {
+        let mut bare = def_named("Restart nginx");
+        bare.command = "sudo systemctl restart nginx".to_string();
+        let mut confirmed = def_named("Reboot");
+        confirmed.command = "sudo reboot".to_string();
+        confirmed.confirm = loader::Confirm::Enabled(true);
+        let mut lookalike = def_named("Edit sudoers");
+        lookalike.command = "visudo".to_string();
+        let commands = [bare, confirmed, lookalike];
+        let warnings = sudo_warnings(commands.iter());
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("Restart nginx"));
+        assert!(warnings[0].contains("uses sudo without confirm"));
+    }
+
+    #[test]
+    fn selecting_an_alternative_runs_that_command() {
+        // The run records usage; keep that out of the real state dir.
+        let state_home = tempfile::tempdir().unwrap();
+        env::set_var("XDG_STATE_HOME", state_home.path());
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("first");
+        let second = dir.path().join("second");
+        let mut def = def_named("Pick one");
+        def.alternatives = vec![
+            format!("touch {}", first.display()),
+            format!("touch {}", second.display()),
+        ];
+        let cli_args = args_from(&[]);
+        let config = AppConfig {
+            // head -n1 stands in for a filter that picks the first line.
+            filter_command: "head -n1".to_string(),
+            ..AppConfig::default()
+        };
+        run_selection(&def, &cli_args, &config).unwrap();
+        assert!(first.exists());
+        assert!(!second.exists());
+    }
+
+    #[test]
+    fn table_rows_align_their_columns() {
+        let mut short = def_named("Short");
+        short.tags = vec!["a".to_string()];
+        let mut long = def_named("A much longer description");
+        long.tags = vec!["infra".to_string(), "slow".to_string()];
+        let table = list_table(&[short, long], 120);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 2);
+        // Both tag columns start at the same offset, two spaces past the
+        // widest description.
+        let tag_column = "A much longer description".len() + 2;
+        assert_eq!(&lines[0][tag_column..tag_column + 2], "#a");
+        assert_eq!(&lines[1][tag_column..tag_column + 6], "#infra");
+        // Both file columns line up too.
+        assert_eq!(
+            lines[0].find("/tmp/test.toml"),
+            lines[1].find("/tmp/test.toml")
+        );
+    }
+
+    #[test]
+    fn empty_tables_get_a_friendly_message() {
+        assert_eq!(list_table(&[], 80), "No commands to list\n");
+    }
+
+    #[test]
+    fn long_file_cells_truncate_to_the_width_budget() {
+        assert_eq!(truncate_column("short", 10), "short");
+        let cell = truncate_column(&"x".repeat(30), 10);
+        assert_eq!(cell.chars().count(), 10);
+        assert!(cell.ends_with('…'));
+    }
+
+    #[test]
+    fn file_and_line_renders_the_real_header_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("jump.toml");
+        std::fs::write(
+            &path,
+            "# prelude\n\n[[commands]]\ndescription = \"Jump here\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        let commands = loader::load_file(&path, true, loader::DuplicatePolicy::Error, false).unwrap();
+        let rendered = file_and_line(&commands["Jump here"]);
+        assert_eq!(rendered, format!("{}:3", path.display()));
+    }
+
+    #[test]
+    fn pre_exec_hook_allows_on_success() {
+        let config = AppConfig {
+            pre_exec: Some("exit 0".to_string()),
+            ..AppConfig::default()
+        };
+        assert!(run_pre_exec_hook(&config, &def_named("x")).is_ok());
+    }
+
+    #[test]
+    fn pre_exec_hook_vetoes_on_failure() {
+        let config = AppConfig {
+            pre_exec: Some("exit 3".to_string()),
+            ..AppConfig::default()
+        };
+        let err = run_pre_exec_hook(&config, &def_named("x")).unwrap_err();
+        assert!(err.to_string().contains("blocked execution"));
+    }
+
+    #[test]
+    fn exec_template_substitutes_command_and_file() {
+        let def = def_named("deploy");
+        let rendered = render_exec_template("echo {} | pbcopy # {file}", &def);
+        assert_eq!(rendered, "echo true | pbcopy # /tmp/test.toml");
+    }
+
+    #[test]
+    fn priority_sort_floats_high_and_sinks_negative() {
+        let mut low = def_named("low");
+        low.priority = -5;
+        let mut high = def_named("high");
+        high.priority = 10;
+        let zero_a = def_named("a");
+        let zero_b = def_named("b");
+        let mut commands = vec![low, zero_b, high, zero_a];
+        sort_commands(&mut commands, SortMode::Priority, false);
+        let names: Vec<&str> =
+            commands.iter().map(|d| d.description.as_str()).collect();
+        assert_eq!(names, vec!["high", "a", "b", "low"]);
+    }
+
+    #[test]
+    fn empty_messages_name_the_actual_problem() {
+        let summary = loader::LoadSummary::default();
+        assert!(empty_message(false, &summary).contains("No snippet directory"));
+        assert!(empty_message(true, &summary).contains("No snippet files (*.toml)"));
+        let unparsed = loader::LoadSummary {
+            files_seen: 2,
+            files_parsed: 0,
+            snippets: 0,
+        };
+        assert!(empty_message(true, &unparsed).contains("could be parsed"));
+        let filtered = loader::LoadSummary {
+            files_seen: 2,
+            files_parsed: 2,
+            snippets: 0,
+        };
+        assert_eq!(empty_message(true, &filtered), "No command snippets found");
+    }
+
+    #[test]
+    fn duplicate_errors_classify_for_json_output() {
+        let dir = tempfile::tempdir().unwrap();
+        let snippet = "[[commands]]\ndescription = \"Twin\"\ncommand = \"true\"\n";
+        std::fs::write(dir.path().join("a.toml"), snippet).unwrap();
+        std::fs::write(dir.path().join("b.toml"), snippet).unwrap();
+        let err: anyhow::Error = loader::load_commands(
+            dir.path(),
+            true,
+            false,
+            DuplicatePolicy::Error,
+            false,
+            None,
+        )
+        .expect_err("duplicate descriptions should not load")
+        .into();
+        assert_eq!(error_kind(&err), "duplicate");
+        assert_eq!(error_kind(&anyhow::anyhow!("boom")), "other");
+    }
+
+    #[test]
+    fn status_only_operations_keep_stdout_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[commands]]\ndescription = \"Quiet\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        // The test binary lives in target/debug/deps; the cmdy binary one
+        // level up, built alongside it.
+        let mut binary = std::env::current_exe().unwrap();
+        binary.pop();
+        if binary.ends_with("deps") {
+            binary.pop();
+        }
+        binary.push("cmdy");
+        let output = Command::new(&binary)
+            .args(["--dir"])
+            .arg(dir.path())
+            .args(["--dry-run", "run", "Quiet"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(
+            output.stdout.is_empty(),
+            "stdout not empty: {:?}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(stderr.contains("Would execute"), "stderr: {stderr:?}");
+    }
+
+    #[test]
+    fn syntax_check_flags_commands_the_shell_rejects() {
+        let mut broken = def_named("broken");
+        broken.command = "echo 'unclosed".to_string();
+        let problem = syntax_violation(&broken).expect("sh -n should reject this");
+        assert!(problem.contains("broken"), "problem: {problem:?}");
+        assert!(problem.contains("/tmp/test.toml"));
+
+        let mut fine = def_named("fine");
+        fine.command = "echo {{name}} | wc -l".to_string();
+        assert!(syntax_violation(&fine).is_none());
+    }
+
+    #[test]
+    fn configured_default_action_applies_unless_run_flags_force_it() {
+        let config = AppConfig {
+            default_action: config::DefaultAction::Clip,
+            ..AppConfig::default()
+        };
+        let bare = CliArgs::parse_from(["cmdy"]);
+        assert_eq!(default_action(&config, &bare), SelectionAction::Clip);
+        let dry = CliArgs::parse_from(["cmdy", "--dry-run"]);
+        assert_eq!(default_action(&config, &dry), SelectionAction::Run);
+        // Every run-only flag must force the Run path, not just --dry-run.
+        for flags in [
+            &["cmdy", "--print-command"][..],
+            &["cmdy", "--print-path"],
+            &["cmdy", "--print-file-and-line"],
+            &["cmdy", "--explain"],
+            &["cmdy", "--edit-before-run"],
+            &["cmdy", "--exec", "echo {}"],
+            &["cmdy", "--watch", "1s"],
+        ] {
+            let run_flavored = CliArgs::parse_from(flags);
+            assert_eq!(
+                default_action(&config, &run_flavored),
+                SelectionAction::Run,
+                "{flags:?} should force Run"
+            );
+        }
+        assert_eq!(
+            default_action(&AppConfig::default(), &bare),
+            SelectionAction::Run
+        );
+    }
+
+    #[test]
+    fn dumped_config_round_trips() {
+        let config = AppConfig {
+            filter_command: "gum filter".to_string(),
+            directories: vec![PathBuf::from("/tmp/snippets")],
+            max_depth: Some(2),
+            filter_output: Some(config::FilterOutput {
+                delimiter: ":".to_string(),
+                field: 0,
+            }),
+            ..AppConfig::default()
+        };
+        let dumped = dump_config(&config).unwrap();
+        let reloaded: AppConfig = toml::from_str(&dumped).unwrap();
+        assert_eq!(reloaded, config);
+    }
+
+    #[test]
+    fn edit_before_run_round_trips_through_a_scripted_editor() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let rewrite = dir.path().join("rewrite.sh");
+        std::fs::write(&rewrite, "#!/bin/sh\nprintf 'echo edited' > \"$1\"\n").unwrap();
+        std::fs::set_permissions(&rewrite, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config = AppConfig {
+            editor: Some(rewrite.display().to_string()),
+            ..AppConfig::default()
+        };
+        let edited = edit_command_in_editor(&config, "echo original").unwrap();
+        assert_eq!(edited.as_deref(), Some("echo edited"));
+
+        // An editor that saves an empty file means "don't run anything".
+        let truncate = dir.path().join("truncate.sh");
+        std::fs::write(&truncate, "#!/bin/sh\n: > \"$1\"\n").unwrap();
+        std::fs::set_permissions(&truncate, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let config = AppConfig {
+            editor: Some(truncate.display().to_string()),
+            ..AppConfig::default()
+        };
+        assert!(edit_command_in_editor(&config, "echo original").unwrap().is_none());
+    }
+
+    #[test]
+    fn required_binaries_cover_each_pipeline_segment() {
+        let binaries = required_binaries(
+            "FOO=1 kubectl get pods | grep Running && sudo systemctl restart nginx; echo done",
+        );
+        assert_eq!(binaries, vec!["kubectl", "grep", "systemctl", "echo"]);
+        // Unresolved shell syntax is skipped rather than reported missing.
+        assert!(required_binaries("$HOME/bin/tool run").is_empty());
+    }
+
+    #[test]
+    fn disabled_snippets_only_appear_under_list_all() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[commands]]\ndescription = \"Active\"\ncommand = \"true\"\n\n[[commands]]\ndescription = \"Parked\"\ncommand = \"true\"\nenabled = false\n",
+        )
+        .unwrap();
+        let mut binary = std::env::current_exe().unwrap();
+        binary.pop();
+        if binary.ends_with("deps") {
+            binary.pop();
+        }
+        binary.push("cmdy");
+        let plain = Command::new(&binary)
+            .args(["--dir"])
+            .arg(dir.path())
+            .arg("list")
+            .output()
+            .unwrap();
+        let plain_stdout = String::from_utf8_lossy(&plain.stdout);
+        assert!(plain_stdout.contains("Active"));
+        assert!(!plain_stdout.contains("Parked"), "stdout: {plain_stdout:?}");
+        let all = Command::new(&binary)
+            .args(["--dir"])
+            .arg(dir.path())
+            .args(["list", "--all"])
+            .output()
+            .unwrap();
+        let all_stdout = String::from_utf8_lossy(&all.stdout);
+        assert!(all_stdout.contains("Parked (disabled)"), "stdout: {all_stdout:?}");
+    }
+
+    #[test]
+    fn check_honors_excluded_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let noisy = dir.path().join("noisy");
+        std::fs::create_dir(&noisy).unwrap();
+        std::fs::write(
+            dir.path().join("good.toml"),
+            "[[commands]]\ndescription = \"Fine\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        // Unparsable, so check would exit 1 if the walk reached it.
+        std::fs::write(noisy.join("bad.toml"), "this is not [ valid toml").unwrap();
+        let config_home = tempfile::tempdir().unwrap();
+        let config_dir = config_home.path().join("cmdy");
+        std::fs::create_dir(&config_dir).unwrap();
+        std::fs::write(
+            config_dir.join("cmdy.toml"),
+            format!(
+                "recursive = true\nexclude_directories = [{:?}]\n",
+                noisy.display()
+            ),
+        )
+        .unwrap();
+        let mut binary = std::env::current_exe().unwrap();
+        binary.pop();
+        if binary.ends_with("deps") {
+            binary.pop();
+        }
+        binary.push("cmdy");
+        let output = Command::new(&binary)
+            .env("XDG_CONFIG_HOME", config_home.path())
+            .args(["--dir"])
+            .arg(dir.path())
+            .arg("check")
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "stderr: {stderr:?}");
+        assert!(stderr.contains("OK: 1 commands"), "stderr: {stderr:?}");
+    }
+
+    #[test]
+    fn quiet_mode_silences_the_check_acknowledgment() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[commands]]\ndescription = \"Hushed\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        let mut binary = std::env::current_exe().unwrap();
+        binary.pop();
+        if binary.ends_with("deps") {
+            binary.pop();
+        }
+        binary.push("cmdy");
+        let output = Command::new(&binary)
+            .args(["--dir"])
+            .arg(dir.path())
+            .args(["--quiet", "check"])
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert!(
+            output.stdout.is_empty(),
+            "stdout not empty: {:?}",
+            String::from_utf8_lossy(&output.stdout)
+        );
+        assert!(
+            output.stderr.is_empty(),
+            "stderr not empty: {:?}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    #[test]
+    fn intervals_parse_with_and_without_units() {
+        assert_eq!(parse_interval("5").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_interval("5s").unwrap(), Duration::from_secs(5));
+        assert_eq!(parse_interval("500ms").unwrap(), Duration::from_millis(500));
+        assert_eq!(parse_interval("2m").unwrap(), Duration::from_secs(120));
+        assert_eq!(parse_interval("1h").unwrap(), Duration::from_secs(3600));
+        assert!(parse_interval("5d").is_err());
+        assert!(parse_interval("fast").is_err());
+    }
+
+    #[test]
+    fn tag_files_merge_with_tag_flags() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("team.tags");
+        std::fs::write(&path, "# shared filters\ngit\n\n  work\n").unwrap();
+        let tags = read_tag_file(&path).unwrap();
+        assert_eq!(tags, vec!["git".to_string(), "work".to_string()]);
+        let mut args = args_from(&["--tag", "deploy"]);
+        args.tags.extend(tags);
+        assert_eq!(args.tags, vec!["deploy", "git", "work"]);
+        assert!(read_tag_file(Path::new("/no/such/file.tags")).is_err());
+    }
+
+    #[test]
+    fn fuzzy_run_accepts_a_unique_subsequence() {
+        let commands = [
+            def_named("Deploy Production Staging"),
+            def_named("Restart nginx"),
+        ];
+        let def = find_run_target(&commands, "dps", false).unwrap();
+        assert_eq!(def.description, "Deploy Production Staging");
+        // An exact description still matches first.
+        let def = find_run_target(&commands, "Restart nginx", false).unwrap();
+        assert_eq!(def.description, "Restart nginx");
+    }
+
+    #[test]
+    fn ambiguous_fuzzy_run_lists_the_candidates() {
+        let commands = [def_named("Deploy east"), def_named("Deploy west")];
+        let err = find_run_target(&commands, "deploy", false)
+            .expect_err("two candidates should be ambiguous");
+        let message = err.to_string();
+        assert!(message.contains("ambiguous"));
+        assert!(message.contains("Deploy east"));
+        assert!(message.contains("Deploy west"));
+    }
+
+    #[test]
+    fn fuzzy_run_rejects_no_match_and_exact_mode() {
+        let commands = [def_named("Deploy")];
+        assert!(find_run_target(&commands, "xyz", false).is_err());
+        // --exact refuses what fuzzy would have accepted.
+        assert!(find_run_target(&commands, "dply", true).is_err());
+        assert!(find_run_target(&commands, "dply", false).is_ok());
+    }
+
+    #[test]
+    fn extra_args_are_appended_quoted() {
+        let def = def_named("deploy");
+        let unchanged = with_extra_args(&def, &[]);
+        assert_eq!(unchanged.command, def.command);
+        let extended = with_extra_args(
+            &def,
+            &["--dry-run".to_string(), "two words".to_string()],
+        );
+        assert_eq!(
+            extended.command,
+            format!("{} --dry-run 'two words'", def.command)
+        );
+    }
+
+    #[test]
+    fn tag_patterns_match_exactly_or_by_trailing_glob() {
+        assert!(tag_matches("git", "git"));
+        assert!(!tag_matches("git", "github"));
+        assert!(tag_matches("experimental*", "experimental"));
+        assert!(tag_matches("experimental*", "experimental-ui"));
+        assert!(!tag_matches("experimental*", "stable"));
+    }
+
+    #[test]
+    fn glob_exclusions_drop_whole_tag_families() {
+        let mut ui = def_named("ui");
+        ui.tags = vec!["experimental-ui".to_string()];
+        let mut api = def_named("api");
+        api.tags = vec!["experimental-api".to_string()];
+        let mut stable = def_named("stable");
+        stable.tags = vec!["stable".to_string()];
+        let mut commands = vec![ui, api, stable];
+        let excluded = ["experimental*".to_string()];
+        commands.retain(|def| {
+            !excluded
+                .iter()
+                .any(|pattern| def.tags.iter().any(|tag| tag_matches(pattern, tag)))
+        });
+        let names: Vec<&str> =
+            commands.iter().map(|def| def.description.as_str()).collect();
+        assert_eq!(names, vec!["stable"]);
+    }
+
+    #[test]
+    fn tag_prefixes_match_case_insensitively() {
+        let mut def = def_named("deploy");
+        def.tags = vec!["Prod-EU".to_string()];
+        assert!(has_tag_prefix(&def, "prod"));
+        assert!(has_tag_prefix(&def, "PROD-eu"));
+        assert!(!has_tag_prefix(&def, "staging"));
+        // A prefix matches the start of a tag, not the middle.
+        assert!(!has_tag_prefix(&def, "eu"));
+    }
+
+    #[test]
+    fn favorites_lead_the_list() {
+        let mut tagged = def_named("zeta");
+        tagged.tags = vec!["favorite".to_string()];
+        let mut commands = vec![def_named("alpha"), tagged, def_named("mid")];
+        sort_commands(&mut commands, SortMode::Alphabetical, false);
+        float_favorites(&mut commands, "favorite");
+        assert_eq!(commands[0].description, "zeta");
+        // The rest keep their alphabetical order.
+        assert_eq!(commands[1].description, "alpha");
+        assert_eq!(commands[2].description, "mid");
+    }
+
+    #[test]
+    fn limit_caps_the_sorted_list() {
+        let mut commands = vec![def_named("b"), def_named("a"), def_named("c")];
+        sort_commands(&mut commands, SortMode::Alphabetical, false);
+        limit_commands(&mut commands, Some(2));
+        let names: Vec<&str> =
+            commands.iter().map(|d| d.description.as_str()).collect();
+        assert_eq!(names, vec!["a", "b"]);
+        limit_commands(&mut commands, None);
+        assert_eq!(commands.len(), 2);
+    }
+
+    #[test]
+    fn reverse_inverts_the_sorted_order() {
+        let mut forward = vec![def_named("b"), def_named("c"), def_named("a")];
+        sort_commands(&mut forward, SortMode::Alphabetical, false);
+        let mut backward = forward.clone();
+        sort_commands(&mut backward, SortMode::Alphabetical, true);
+        let forward_names: Vec<&str> =
+            forward.iter().map(|d| d.description.as_str()).collect();
+        let mut backward_names: Vec<&str> =
+            backward.iter().map(|d| d.description.as_str()).collect();
+        backward_names.reverse();
+        assert_eq!(forward_names, vec!["a", "b", "c"]);
+        assert_eq!(forward_names, backward_names);
+    }
+
+    #[test]
+    fn placeholder_lint_flags_prompts_and_orphan_defaults() {
+        let mut def = def_named("ssh somewhere");
+        def.command = "ssh {{user}}@{{host}}".to_string();
+        def.defaults.insert("user".to_string(), "root".to_string());
+        def.defaults.insert("hsot".to_string(), "x".to_string());
+        let findings = placeholder_findings(&def, true);
+        assert_eq!(findings.len(), 2);
+        assert!(findings.iter().any(|f| f.contains("{{host}}")));
+        assert!(findings.iter().any(|f| f.contains("hsot")));
+        // Without prompts, only the orphan default is reported.
+        assert_eq!(placeholder_findings(&def, false).len(), 1);
+    }
+
+    #[test]
+    fn allowed_tags_pass_the_check() {
+        let mut def = def_named("deploy");
+        def.tags = vec!["git".to_string()];
+        let allowed = vec!["git".to_string(), "work".to_string()];
+        assert!(unknown_tag_violations(std::iter::once(&def), &allowed).is_empty());
+    }
+
+    #[test]
+    fn unknown_tags_are_flagged_with_their_file() {
+        let mut def = def_named("deploy");
+        def.tags = vec!["stagign".to_string()];
+        let allowed = vec!["staging".to_string()];
+        let violations = unknown_tag_violations(std::iter::once(&def), &allowed);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0].contains("stagign"));
+        assert!(violations[0].contains("/tmp/test.toml"));
+    }
+
+    #[test]
+    fn var_flags_parse_and_reject_garbage() {
+        let vars = parse_vars(&["host=example.com".to_string(), "a=b=c".to_string()])
+            .unwrap();
+        assert_eq!(vars["host"], "example.com");
+        assert_eq!(vars["a"], "b=c");
+        assert!(parse_vars(&["nonsense".to_string()]).is_err());
+    }
+
+    #[test]
+    fn random_index_stays_in_bounds() {
+        for len in [1, 2, 7, 100] {
+            for _ in 0..20 {
+                assert!(random_index(len) < len);
+            }
+        }
+        assert_eq!(random_index(1), 0);
+    }
+
+    #[test]
+    fn first_match_prefers_exact_descriptions() {
+        let commands = vec![def_named("deploy"), def_named("deploy staging")];
+        let def = find_first_match(&commands, "deploy").unwrap();
+        assert_eq!(def.description, "deploy");
+    }
+
+    #[test]
+    fn first_match_accepts_a_unique_substring() {
+        let commands = vec![def_named("deploy staging"), def_named("run tests")];
+        let def = find_first_match(&commands, "staging").unwrap();
+        assert_eq!(def.description, "deploy staging");
+    }
+
+    #[test]
+    fn first_match_rejects_ambiguity_and_misses() {
+        let commands = vec![def_named("deploy staging"), def_named("deploy prod")];
+        assert!(find_first_match(&commands, "deploy").is_err());
+        assert!(find_first_match(&commands, "nothing").is_err());
+    }
+
+    #[test]
+    fn repeated_tag_flags_accumulate() {
+        let cli_args = args_from(&["--tag", "a", "--tag", "b"]);
+        assert_eq!(cli_args.tags, vec!["a", "b"]);
+    }
+
+    #[test]
+    fn comma_separated_tags_match_repeated_flags() {
+        let comma = args_from(&["--tag", "a,b"]);
+        let repeated = args_from(&["--tag", "a", "--tag", "b"]);
+        assert_eq!(comma.tags, repeated.tags);
+        let excluded = args_from(&["--not-tag", "slow,net"]);
+        assert_eq!(excluded.not_tags, vec!["slow", "net"]);
+    }
+}
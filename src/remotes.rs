@@ -0,0 +1,112 @@
+//! Remote snippet libraries, behind the `remotes` feature.
+//!
+//! `remotes` in cmdy.toml lists HTTP(S) URLs of snippet files. Each is
+//! fetched on startup (with a short timeout) and cached under the user's
+//! cache directory, so a team-shared library keeps working offline: when
+//! the fetch fails, the cached copy is used instead.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// How long to wait for a remote before falling back to the cache. Startup
+/// blocks on this, so it errs on the short side.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The cache directory for fetched remotes: `$XDG_CACHE_HOME/cmdy/remotes`
+/// (or `~/.cache/cmdy/remotes`).
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = match std::env::var("XDG_CACHE_HOME") {
+        Ok(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var("HOME").ok()?).join(".cache"),
+    };
+    Some(base.join("cmdy").join("remotes"))
+}
+
+/// The cache file for one URL: a hash of the URL keeps the name filesystem
+/// safe while staying stable across runs.
+fn cache_file(url: &str) -> Option<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    url.hash(&mut hasher);
+    Some(cache_dir()?.join(format!("{:016x}.toml", hasher.finish())))
+}
+
+/// Fetches each remote into the cache and returns the cache files that are
+/// ready to load. Failures are warnings, not errors: a fetch that fails
+/// falls back to the cached copy when one exists, and is skipped (with a
+/// warning) when it doesn't, so a flaky network never blocks the picker.
+pub fn fetch_remotes(remotes: &[String]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for url in remotes {
+        let Some(path) = cache_file(url) else {
+            eprintln!("Warning: no cache directory for remote {url}");
+            continue;
+        };
+        match fetch(url) {
+            Ok(body) => {
+                if let Some(parent) = path.parent() {
+                    let _ = fs::create_dir_all(parent);
+                }
+                if let Err(err) = fs::write(&path, &body) {
+                    eprintln!("Warning: could not cache remote {url}: {err}");
+                }
+            }
+            Err(err) => {
+                if path.is_file() {
+                    eprintln!("Note: could not fetch {url} ({err}); using cached copy");
+                } else {
+                    eprintln!("Warning: skipping remote {url}: {err}");
+                }
+            }
+        }
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    files
+}
+
+fn fetch(url: &str) -> Result<String, Box<ureq::Error>> {
+    ureq::AgentBuilder::new()
+        .timeout(FETCH_TIMEOUT)
+        .build()
+        .get(url)
+        .call()?
+        .into_string()
+        .map_err(|err| Box::new(ureq::Error::from(err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_names_are_stable_and_distinct() {
+        let a = cache_file("https://example.com/a.toml").unwrap();
+        let again = cache_file("https://example.com/a.toml").unwrap();
+        let b = cache_file("https://example.com/b.toml").unwrap();
+        assert_eq!(a, again);
+        assert_ne!(a, b);
+        assert_eq!(a.extension().and_then(|ext| ext.to_str()), Some("toml"));
+    }
+
+    #[test]
+    fn unreachable_remote_falls_back_to_the_cache() {
+        let scratch = tempfile::tempdir().unwrap();
+        // Point the cache at a scratch directory so the test is hermetic.
+        std::env::set_var("XDG_CACHE_HOME", scratch.path());
+        let url = "http://127.0.0.1:1/commands.toml".to_string();
+        let path = cache_file(&url).unwrap();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            "[[commands]]\ndescription = \"Cached\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        let files = fetch_remotes(std::slice::from_ref(&url));
+        assert_eq!(files, vec![path]);
+        std::env::remove_var("XDG_CACHE_HOME");
+    }
+}
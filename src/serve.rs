@@ -0,0 +1,202 @@
+//! Long-lived backend mode (`cmdy --serve`), behind the `serve` feature.
+//!
+//! The protocol is newline-delimited JSON: one request per line on stdin,
+//! one response per line on stdout.
+//!
+//! Requests:
+//!   {"op": "list"}
+//!   {"op": "run", "name": "<description or id>"}
+//!
+//! Responses:
+//!   {"ok": true, "commands": [{"description", "command", "tags", "source_file"}, ...]}
+//!   {"ok": true, "status": <exit code>}
+//!   {"ok": false, "error": "<message>"}
+//!
+//! Snippet files are re-scanned when anything in a watched directory
+//! changes, so an editor plugin always sees the current state.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use notify::{RecursiveMode, Watcher};
+use serde::Deserialize;
+
+use crate::exec;
+use crate::loader::{self, CommandDef, DuplicatePolicy};
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+enum Request {
+    List,
+    Run { name: String },
+}
+
+/// Runs the serve loop over real stdin/stdout, watching `scan_dirs` for
+/// changes. Returns when stdin reaches EOF.
+pub fn serve(
+    scan_dirs: &[PathBuf],
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+) -> Result<()> {
+    let dirty = Arc::new(AtomicBool::new(true));
+    let watcher_dirty = Arc::clone(&dirty);
+    let mut watcher = notify::recommended_watcher(move |_| {
+        watcher_dirty.store(true, Ordering::SeqCst);
+    })
+    .context("Could not create file watcher")?;
+    for dir in scan_dirs {
+        // A directory that vanishes mid-session just stops being watched.
+        let _ = watcher.watch(dir, RecursiveMode::Recursive);
+    }
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    run_loop(
+        stdin.lock(),
+        stdout.lock(),
+        scan_dirs,
+        strict,
+        recursive,
+        policy,
+        &dirty,
+    )
+}
+
+/// The request/response loop, separated from the watcher and real stdio so
+/// it can be driven in tests.
+#[allow(clippy::too_many_arguments)]
+fn run_loop(
+    reader: impl BufRead,
+    mut writer: impl Write,
+    scan_dirs: &[PathBuf],
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+    dirty: &AtomicBool,
+) -> Result<()> {
+    let mut commands: Vec<CommandDef> = Vec::new();
+    for line in reader.lines() {
+        let line = line.context("Could not read request")?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if dirty.swap(false, Ordering::SeqCst) {
+            match load_all(scan_dirs, strict, recursive, policy) {
+                Ok(loaded) => commands = loaded,
+                Err(err) => {
+                    // Keep serving the previous snapshot; report the error.
+                    dirty.store(true, Ordering::SeqCst);
+                    writeln!(writer, "{}", error_response(&err.to_string()))?;
+                    writer.flush()?;
+                    continue;
+                }
+            }
+        }
+        writeln!(writer, "{}", handle_request(&line, &commands))?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+fn load_all(
+    scan_dirs: &[PathBuf],
+    strict: bool,
+    recursive: bool,
+    policy: DuplicatePolicy,
+) -> Result<Vec<CommandDef>> {
+    let mut commands = Vec::new();
+    for dir in scan_dirs {
+        let mut loaded = loader::load_commands(dir, strict, recursive, policy, false, None)?;
+        loader::expand_macros(&mut loaded, 100)?;
+        loader::resolve_bases(&mut loaded)?;
+        commands.extend(loaded.into_values().filter(|def| def.enabled));
+    }
+    Ok(commands)
+}
+
+fn handle_request(line: &str, commands: &[CommandDef]) -> String {
+    let request: Request = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(err) => return error_response(&format!("Bad request: {err}")),
+    };
+    match request {
+        Request::List => {
+            let listed: Vec<serde_json::Value> = commands
+                .iter()
+                .map(|def| {
+                    serde_json::json!({
+                        "description": def.description,
+                        "command": def.command,
+                        "tags": def.tags,
+                        "source_file": def.source_file,
+                    })
+                })
+                .collect();
+            serde_json::json!({ "ok": true, "commands": listed }).to_string()
+        }
+        Request::Run { name } => {
+            let Some(def) = commands
+                .iter()
+                .find(|def| def.key() == name || def.description == name)
+            else {
+                return error_response(&format!("No command named {name:?}"));
+            };
+            match exec::execute_command(def, false, false, &Default::default(), false, false) {
+                Ok(Some(outcome)) => serde_json::json!({
+                    "ok": true,
+                    "status": outcome.status.code().unwrap_or(-1),
+                })
+                .to_string(),
+                Ok(None) => error_response("Aborted"),
+                Err(err) => error_response(&err.to_string()),
+            }
+        }
+    }
+}
+
+fn error_response(message: &str) -> String {
+    serde_json::json!({ "ok": false, "error": message }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn list_request_round_trips_through_the_loop() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("a.toml"),
+            "[[commands]]\ndescription = \"Served\"\ncommand = \"true\"\n",
+        )
+        .unwrap();
+        let dirty = AtomicBool::new(true);
+        let input = Cursor::new(b"{\"op\": \"list\"}\n".to_vec());
+        let mut output = Vec::new();
+        run_loop(
+            input,
+            &mut output,
+            &[dir.path().to_path_buf()],
+            true,
+            false,
+            DuplicatePolicy::Error,
+            &dirty,
+        )
+        .unwrap();
+        let response: serde_json::Value =
+            serde_json::from_slice(&output).unwrap();
+        assert_eq!(response["ok"], true);
+        assert_eq!(response["commands"][0]["description"], "Served");
+    }
+
+    #[test]
+    fn bad_requests_get_an_error_response() {
+        let response = handle_request("not json", &[]);
+        let parsed: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(parsed["ok"], false);
+    }
+}
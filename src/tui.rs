@@ -0,0 +1,225 @@
+//! Built-in multi-pane browser (`cmdy --tui`), behind the `tui` feature.
+//!
+//! An alternative to the external filter program: the left pane lists the
+//! commands grouped by their first tag, the right pane previews the
+//! selected command. `/` starts a filter, Enter accepts the selection,
+//! and `q` or Esc leaves without running anything.
+
+use std::io;
+
+use anyhow::{Context, Result};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::Terminal;
+
+use crate::loader::CommandDef;
+
+/// What the browser is doing with keystrokes: navigating the list, or
+/// editing the filter after `/`.
+enum Mode {
+    Browse,
+    Filter,
+}
+
+/// Runs the browser over `commands` and returns the accepted selection,
+/// or `None` when the user leaves without choosing.
+pub fn pick(commands: &[CommandDef]) -> Result<Option<CommandDef>> {
+    enable_raw_mode().context("Could not enter raw terminal mode")?;
+    let mut stderr = io::stderr();
+    crossterm::execute!(stderr, EnterAlternateScreen)
+        .context("Could not enter the alternate screen")?;
+    let backend = ratatui::backend::CrosstermBackend::new(stderr);
+    let mut terminal = Terminal::new(backend).context("Could not start the TUI")?;
+    let picked = run_browser(&mut terminal, commands);
+    // Restore the terminal even when the browser errored.
+    let _ = disable_raw_mode();
+    let _ = crossterm::execute!(terminal.backend_mut(), LeaveAlternateScreen);
+    picked
+}
+
+fn run_browser(
+    terminal: &mut Terminal<impl ratatui::backend::Backend>,
+    commands: &[CommandDef],
+) -> Result<Option<CommandDef>> {
+    // Grouping is by sort: commands sharing a first tag sit together, with
+    // the tag shown on each row.
+    let mut ordered: Vec<&CommandDef> = commands.iter().collect();
+    ordered.sort_by_key(|def| (group_of(def).to_string(), def.description.clone()));
+
+    let mut mode = Mode::Browse;
+    let mut filter = String::new();
+    let mut state = ListState::default();
+    state.select(Some(0));
+
+    loop {
+        let visible = filter_indices(&ordered, &filter);
+        let selected = state.selected().unwrap_or(0).min(visible.len().saturating_sub(1));
+        state.select(if visible.is_empty() { None } else { Some(selected) });
+
+        terminal.draw(|frame| {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+                .split(frame.area());
+
+            let items: Vec<ListItem> = visible
+                .iter()
+                .map(|&index| {
+                    let def = ordered[index];
+                    ListItem::new(format!("[{}] {}", group_of(def), def.description))
+                })
+                .collect();
+            let title = match mode {
+                Mode::Filter => format!("Commands (/{filter})"),
+                Mode::Browse if filter.is_empty() => "Commands".to_string(),
+                Mode::Browse => format!("Commands (filtered: {filter})"),
+            };
+            let list = List::new(items)
+                .block(Block::default().borders(Borders::ALL).title(title))
+                .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+            frame.render_stateful_widget(list, panes[0], &mut state);
+
+            let preview = match state.selected().and_then(|i| visible.get(i)) {
+                Some(&index) => {
+                    let def = ordered[index];
+                    vec![
+                        Line::from(def.description.clone()),
+                        Line::from(""),
+                        Line::from(def.command.clone()),
+                        Line::from(""),
+                        Line::from(format!("tags: {}", def.tags.join(", "))),
+                        Line::from(format!("from: {}", def.source_file.display())),
+                    ]
+                }
+                None => vec![Line::from("No matches")],
+            };
+            let paragraph = Paragraph::new(preview)
+                .wrap(Wrap { trim: false })
+                .block(Block::default().borders(Borders::ALL).title("Preview"));
+            frame.render_widget(paragraph, panes[1]);
+        })?;
+
+        let Event::Key(key) = event::read().context("Could not read input")? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        match mode {
+            Mode::Filter => match key.code {
+                KeyCode::Esc => {
+                    filter.clear();
+                    mode = Mode::Browse;
+                }
+                KeyCode::Enter => mode = Mode::Browse,
+                KeyCode::Backspace => {
+                    filter.pop();
+                }
+                KeyCode::Char(c) => filter.push(c),
+                _ => {}
+            },
+            Mode::Browse => match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(None),
+                KeyCode::Char('/') => mode = Mode::Filter,
+                KeyCode::Down | KeyCode::Char('j') => {
+                    let next = state.selected().map_or(0, |i| i + 1);
+                    if next < visible.len() {
+                        state.select(Some(next));
+                    }
+                }
+                KeyCode::Up | KeyCode::Char('k') => {
+                    state.select(state.selected().map(|i| i.saturating_sub(1)));
+                }
+                KeyCode::Enter => {
+                    if let Some(&index) = state.selected().and_then(|i| visible.get(i)) {
+                        return Ok(Some(ordered[index].clone()));
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+}
+
+/// The tag a command is grouped under: its first tag, or "untagged".
+fn group_of(def: &CommandDef) -> &str {
+    def.tags.first().map_or("untagged", String::as_str)
+}
+
+/// The entries still visible under `filter`: a case-insensitive substring
+/// match against description, command, and tags.
+fn filter_indices(ordered: &[&CommandDef], filter: &str) -> Vec<usize> {
+    if filter.is_empty() {
+        return (0..ordered.len()).collect();
+    }
+    let needle = filter.to_lowercase();
+    ordered
+        .iter()
+        .enumerate()
+        .filter(|(_, def)| {
+            def.description.to_lowercase().contains(&needle)
+                || def.command.to_lowercase().contains(&needle)
+                || def.tags.iter().any(|tag| tag.to_lowercase().contains(&needle))
+        })
+        .map(|(index, _)| index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    fn def_with_tags(description: &str, tags: &[&str]) -> CommandDef {
+        CommandDef {
+            description: description.to_string(),
+            command: format!("echo {description}"),
+            id: None,
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: BTreeMap::new(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn filtering_matches_description_command_and_tags() {
+        let defs = [
+            def_with_tags("Deploy", &["work"]),
+            def_with_tags("Restart nginx", &["servers"]),
+        ];
+        let ordered: Vec<&CommandDef> = defs.iter().collect();
+        assert_eq!(filter_indices(&ordered, ""), vec![0, 1]);
+        assert_eq!(filter_indices(&ordered, "deploy"), vec![0]);
+        assert_eq!(filter_indices(&ordered, "servers"), vec![1]);
+        assert!(filter_indices(&ordered, "nothing").is_empty());
+    }
+
+    #[test]
+    fn grouping_uses_the_first_tag() {
+        assert_eq!(group_of(&def_with_tags("A", &["git", "work"])), "git");
+        assert_eq!(group_of(&def_with_tags("B", &[])), "untagged");
+    }
+}
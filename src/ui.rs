@@ -0,0 +1,829 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Once;
+
+use anyhow::{bail, Context, Result};
+
+use crate::config::AppConfig;
+use crate::loader::CommandDef;
+
+/// 256-color palette cycled through for tag coloring.
+const TAG_COLORS: [u8; 8] = [110, 150, 179, 174, 139, 108, 180, 146];
+
+/// Deterministically picks a color for a tag so the same tag is always
+/// rendered the same way.
+fn tag_color(tag: &str) -> u8 {
+    let hash: usize = tag.bytes().map(usize::from).sum();
+    TAG_COLORS[hash % TAG_COLORS.len()]
+}
+
+/// Dims text for secondary labels.
+pub fn dim(text: &str) -> String {
+    format!("\x1b[2m{text}\x1b[0m")
+}
+
+/// Highlights text for the part the eye should land on.
+pub fn highlight(text: &str) -> String {
+    format!("\x1b[1m{text}\x1b[0m")
+}
+
+/// Whether stdout should receive ANSI styling: it must be a terminal and
+/// `NO_COLOR` must be unset.
+pub fn stdout_supports_color() -> bool {
+    use std::io::IsTerminal;
+    std::env::var_os("NO_COLOR").is_none() && std::io::stdout().is_terminal()
+}
+
+/// Renders a tag as a colored `#tag` token.
+pub fn color_tag(tag: &str) -> String {
+    format!("\x1b[38;5;{}m#{}\x1b[0m", tag_color(tag), tag)
+}
+
+/// Collapses a multi-line string to one line for the filter input. The
+/// filter protocol is line-oriented, so a heredoc command (or a stray
+/// newline in a description) must never split across entries.
+fn single_line(text: &str) -> String {
+    text.replace("\r\n", " ").replace(['\n', '\r'], " ")
+}
+
+fn plain_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| format!("#{tag}"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn colored_tags(tags: &[String]) -> String {
+    tags.iter()
+        .map(|tag| color_tag(tag))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn filter_program(filter_command: &str) -> Option<&str> {
+    filter_command.split_whitespace().next()
+}
+
+fn program_name(program: &str) -> &str {
+    Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program)
+}
+
+/// The probe order and default arguments for `filter_command = "auto"`.
+const AUTO_FILTER_CANDIDATES: [&str; 3] = [
+    "fzf --ansi --height=50% --reverse",
+    "sk --ansi --height=50% --reverse",
+    "gum filter",
+];
+
+/// Resolves `filter_command = "auto"` to the first of fzf, sk, or gum
+/// found on PATH, with sensible default arguments for each. When none is
+/// installed the configured default is returned, so the user gets the
+/// usual "could not run" error naming a real tool. Anything other than
+/// `"auto"` passes through untouched.
+pub fn resolve_filter_command(filter_command: &str) -> String {
+    if filter_command != "auto" {
+        return filter_command.to_string();
+    }
+    for candidate in AUTO_FILTER_CANDIDATES {
+        let Some(program) = filter_program(candidate) else {
+            continue;
+        };
+        if binary_on_path(program) {
+            return candidate.to_string();
+        }
+    }
+    crate::config::DEFAULT_FILTER_COMMAND.to_string()
+}
+
+/// Checks whether a program can be found via `$PATH`.
+pub fn binary_on_path(program: &str) -> bool {
+    if program.contains('/') {
+        return std::path::PathBuf::from(program).is_file();
+    }
+    let Ok(path) = std::env::var("PATH") else {
+        return false;
+    };
+    std::env::split_paths(&path).any(|dir| dir.join(program).is_file())
+}
+
+/// The flag known filter programs take for an initial query. Anything not
+/// listed here needs `filter_query_flag` in the config.
+fn query_flag(name: &str) -> Option<&'static str> {
+    match name {
+        "fzf" | "sk" | "skim" | "peco" => Some("--query"),
+        "gum" => Some("--value"),
+        _ => None,
+    }
+}
+
+/// Whether a filter child is currently running; set around the spawn and
+/// wait in [`choose_command`] so the interrupt handler knows what Ctrl-C
+/// means.
+static FILTER_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// Whether Ctrl-C arrived while the filter was up.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static INSTALL_INTERRUPT_HANDLER: Once = Once::new();
+
+/// Installs a process-wide SIGINT handler so Ctrl-C during the filter
+/// doesn't kill cmdy before the child is collected. The filter shares the
+/// foreground process group, so it receives the SIGINT itself and exits;
+/// the handler just keeps cmdy alive long enough for the normal wait to
+/// reap it, after which the terminal is reset. Outside the filter, Ctrl-C
+/// exits with the conventional 130.
+fn install_interrupt_handler() {
+    INSTALL_INTERRUPT_HANDLER.call_once(|| {
+        let result = ctrlc::set_handler(|| {
+            if FILTER_ACTIVE.load(Ordering::SeqCst) {
+                INTERRUPTED.store(true, Ordering::SeqCst);
+            } else {
+                std::process::exit(130);
+            }
+        });
+        if let Err(err) = result {
+            eprintln!("Warning: could not install Ctrl-C handler: {err}");
+        }
+    });
+}
+
+/// Undoes the display state a half-dead filter can leave behind: shows the
+/// cursor, leaves the alternate screen, and clears text attributes.
+fn reset_terminal() {
+    eprint!("\x1b[?25h\x1b[?1049l\x1b[0m");
+    let _ = std::io::stderr().flush();
+}
+
+/// Presents `commands` through the configured filter program and returns the
+/// selection, or `None` if the user aborted without picking anything.
+pub fn choose_command<'a>(
+    commands: &'a [CommandDef],
+    config: &AppConfig,
+    initial_query: Option<&str>,
+    include_tags: &[String],
+    exclude_tags: &[String],
+) -> Result<Option<&'a CommandDef>> {
+    let filter_command = resolve_filter_command(&config.filter_command);
+    let program = filter_program(&filter_command)
+        .context("filter_command is empty")?;
+    let mut args: Vec<String> = filter_command
+        .split_whitespace()
+        .skip(1)
+        .map(String::from)
+        .collect();
+    let name = program_name(program);
+    let is_fzf = name == "fzf";
+    let is_gum = name == "gum";
+
+    if let Some(query) = initial_query {
+        let flag = config
+            .filter_query_flag
+            .as_deref()
+            .or_else(|| query_flag(name));
+        if let Some(flag) = flag {
+            args.push(format!("{flag}={query}"));
+        }
+    }
+    args.extend(
+        auto_select_args(config.auto_select_unique, is_fzf)
+            .iter()
+            .map(|arg| arg.to_string()),
+    );
+    // Both supported filters take --header, so give gum users the same
+    // tag-filter context fzf users get.
+    if (is_fzf || is_gum) && (!include_tags.is_empty() || !exclude_tags.is_empty()) {
+        let header = tag_filter_header(include_tags, exclude_tags);
+        args.push(format!(
+            "--header={}",
+            fit_header(&header, terminal_width())
+        ));
+    }
+
+    // Only fzf (invoked with --ansi by default) is known to render colored
+    // input; other filters would show the escapes as garbage, so they get
+    // plain lines unless the config vouches for them.
+    let ansi = is_fzf || config.filter_supports_ansi;
+
+    // When any command declares an explicit id, identity and label are
+    // decoupled: the key rides along in a tab-separated column (hidden by
+    // fzf via --with-nth) so identical descriptions stay distinguishable.
+    // search_command_text adds the command itself as a further column, so
+    // the match input covers what the command does, not just its label.
+    // Truncation also forces the key column, since two long labels may
+    // collapse to the same prefix.
+    let truncate_to = config.truncate_descriptions.then(terminal_width);
+    let keyed = truncate_to.is_some() || commands.iter().any(|def| def.id.is_some());
+    let searchable = config.search_command_text;
+    // Icons ride in their own leading column so fzf can show them without
+    // letting them pollute the fuzzy match.
+    let iconic = commands.iter().any(|def| def.icon.is_some());
+    if (keyed || searchable || iconic) && is_fzf {
+        args.push("--delimiter=\t".to_string());
+        if iconic {
+            args.push("--with-nth=1,2".to_string());
+            args.push(
+                if searchable { "--nth=2.." } else { "--nth=2" }.to_string(),
+            );
+        } else {
+            args.push("--with-nth=1".to_string());
+            if searchable {
+                args.push("--nth=1..".to_string());
+            }
+        }
+    }
+
+    // With filter_output configured the filter can't be trusted to echo
+    // the line back, so identity moves into a prepended index column and
+    // the map is keyed by index instead of the rendered line.
+    let filter_output = config.filter_output.as_ref();
+    let mut choice_map: HashMap<String, &CommandDef> = HashMap::new();
+    let mut colored_lines: Vec<String> = Vec::new();
+    for (index, def) in commands.iter().enumerate() {
+        let (plain, mut display) = render_line(
+            def,
+            &config.label_template,
+            keyed,
+            searchable,
+            ansi,
+            truncate_to,
+            iconic,
+            config.sort_tags,
+        );
+        match filter_output {
+            Some(spec) => {
+                display = format!("{index}{}{display}", spec.delimiter);
+                choice_map.insert(index.to_string(), def);
+            }
+            None => {
+                choice_map.insert(plain, def);
+            }
+        }
+        colored_lines.push(display);
+    }
+
+    // Armed before the spawn so a Ctrl-C in the window between spawn and
+    // wait is still treated as "interrupt the filter", not "kill cmdy".
+    install_interrupt_handler();
+    FILTER_ACTIVE.store(true, Ordering::SeqCst);
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run filter command {program:?}"))
+        .inspect_err(|_| FILTER_ACTIVE.store(false, Ordering::SeqCst))?;
+
+    // Stream lines from a separate thread so the filter can start showing
+    // results before the whole list has been written; with large snippet
+    // sets this is the difference between fzf appearing instantly and after
+    // a pause.
+    let mut stdin = child.stdin.take().context("Filter has no stdin")?;
+    let writer = std::thread::spawn(move || -> std::io::Result<()> {
+        for line in &colored_lines {
+            if let Err(err) = writeln!(stdin, "{line}") {
+                // The filter may exit (e.g. on Esc) before reading
+                // everything; a broken pipe just means no selection.
+                if err.kind() == std::io::ErrorKind::BrokenPipe {
+                    break;
+                }
+                return Err(err);
+            }
+        }
+        Ok(())
+    });
+
+    let output = child.wait_with_output();
+    FILTER_ACTIVE.store(false, Ordering::SeqCst);
+    if INTERRUPTED.swap(false, Ordering::SeqCst) {
+        // The child is reaped by the wait above; all that's left of the
+        // interrupt is whatever it did to the display.
+        reset_terminal();
+        return Ok(None);
+    }
+    let output = output.context("Could not read filter output")?;
+    match writer.join() {
+        Ok(result) => result.context("Could not stream commands to the filter")?,
+        Err(_) => bail!("Filter writer thread panicked"),
+    }
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let selection = String::from_utf8_lossy(&output.stdout);
+    let selection = selection.trim_end_matches(['\r', '\n']);
+    if selection.is_empty() {
+        return Ok(None);
+    }
+    let key = match filter_output {
+        Some(spec) => selection_key(selection, spec),
+        None => selection.to_string(),
+    };
+    Ok(choice_map.get(&key).copied())
+}
+
+/// A minimal secondary pick over plain strings through the configured
+/// filter program, used for snippet `alternatives`. No columns, colors,
+/// or keys: the selected line is matched back to its option. Returns
+/// `None` when the filter is dismissed without a choice.
+pub fn choose_string(options: &[String], config: &AppConfig) -> Result<Option<String>> {
+    let filter_command = resolve_filter_command(&config.filter_command);
+    let program = filter_program(&filter_command)
+        .context("filter_command is empty")?;
+    let args: Vec<String> = filter_command
+        .split_whitespace()
+        .skip(1)
+        .map(String::from)
+        .collect();
+    install_interrupt_handler();
+    FILTER_ACTIVE.store(true, Ordering::SeqCst);
+    let mut child = Command::new(program)
+        .args(&args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Could not run filter command {program:?}"))
+        .inspect_err(|_| FILTER_ACTIVE.store(false, Ordering::SeqCst))?;
+    {
+        let mut stdin = child.stdin.take().context("Filter has no stdin")?;
+        for option in options {
+            // A broken pipe just means the filter stopped reading early.
+            if writeln!(stdin, "{}", single_line(option)).is_err() {
+                break;
+            }
+        }
+    }
+    let output = child.wait_with_output();
+    FILTER_ACTIVE.store(false, Ordering::SeqCst);
+    if INTERRUPTED.swap(false, Ordering::SeqCst) {
+        reset_terminal();
+        return Ok(None);
+    }
+    let output = output.context("Could not read filter output")?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let selection = String::from_utf8_lossy(&output.stdout);
+    let selection = selection.trim_end_matches(['\r', '\n']);
+    if selection.is_empty() {
+        return Ok(None);
+    }
+    Ok(options
+        .iter()
+        .find(|option| single_line(option) == selection)
+        .cloned())
+}
+
+/// The extra fzf arguments for `auto_select_unique`: `--select-1` picks
+/// a lone match without interaction and `--exit-0` turns an empty match
+/// list into a clean abort. Other filters have no equivalent, so they
+/// get nothing.
+fn auto_select_args(auto_select_unique: bool, is_fzf: bool) -> &'static [&'static str] {
+    if auto_select_unique && is_fzf {
+        &["--select-1", "--exit-0"]
+    } else {
+        &[]
+    }
+}
+
+/// Extracts the lookup field from a selected line under the configured
+/// `filter_output` scheme. A line with too few fields yields the whole
+/// line, which simply fails the lookup.
+fn selection_key(selection: &str, spec: &crate::config::FilterOutput) -> String {
+    selection
+        .split(spec.delimiter.as_str())
+        .nth(spec.field)
+        .unwrap_or(selection)
+        .to_string()
+}
+
+/// Builds the lookup key and displayed line for one command from the
+/// configured label template. The key is always escape-free (fzf strips
+/// ANSI from its output); the display is colored only when the filter can
+/// render it. Both use the same rendered template so selections map back
+/// to the right command. With `icon_column`, the snippet's icon (or a
+/// placeholder space) leads in a column of its own, kept out of matching
+/// by the `--nth` arguments above. With `sort_tags`, tags render in
+/// alphabetical order instead of authored order.
+#[allow(clippy::too_many_arguments)]
+fn render_line(
+    def: &CommandDef,
+    label_template: &str,
+    keyed: bool,
+    searchable: bool,
+    ansi: bool,
+    truncate_to: Option<usize>,
+    icon_column: bool,
+    sort_tags: bool,
+) -> (String, String) {
+    let tags = if sort_tags {
+        let mut sorted = def.tags.clone();
+        sorted.sort();
+        sorted
+    } else {
+        def.tags.clone()
+    };
+    let mut plain =
+        single_line(&apply_label_template(label_template, def, &plain_tags(&tags)));
+    let display_tags = if ansi {
+        colored_tags(&tags)
+    } else {
+        plain_tags(&tags)
+    };
+    let mut display =
+        single_line(&apply_label_template(label_template, def, &display_tags));
+    if let Some(width) = truncate_to {
+        plain = truncate_label(&plain, width);
+        // Cutting a colored label mid-escape would garble the display, so
+        // truncated lines fall back to the plain rendering.
+        display = plain.clone();
+    }
+    if keyed {
+        plain.push_str(&format!("\t{}", def.key()));
+        display.push_str(&format!("\t{}", def.key()));
+    }
+    if searchable {
+        // Tabs inside the command would split the column; flatten them.
+        let text = def.command.replace(['\t', '\n'], " ");
+        plain.push_str(&format!("\t{text}"));
+        display.push_str(&format!("\t{text}"));
+    }
+    if icon_column {
+        // A space keeps icon-less entries aligned with iconed ones.
+        let icon = single_line(def.icon.as_deref().unwrap_or(" "));
+        let styled = if ansi { dim(&icon) } else { icon.clone() };
+        plain = format!("{icon}\t{plain}");
+        display = format!("{styled}\t{display}");
+    }
+    (plain, display)
+}
+
+/// Truncates `label` to `width` characters, ending in an ellipsis.
+fn truncate_label(label: &str, width: usize) -> String {
+    if label.chars().count() <= width {
+        return label.to_string();
+    }
+    let mut truncated: String = label.chars().take(width.saturating_sub(1)).collect();
+    truncated.push('…');
+    truncated
+}
+
+/// The terminal width from `$COLUMNS`, defaulting to 80 columns.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|columns| columns.parse().ok())
+        .filter(|width| *width > 0)
+        .unwrap_or(80)
+}
+
+/// Substitutes the label-template tokens for one command.
+fn apply_label_template(template: &str, def: &CommandDef, tags: &str) -> String {
+    let dir = def
+        .source_file
+        .parent()
+        .and_then(|parent| parent.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    let file = def
+        .source_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("");
+    template
+        .replace("{description}", &def.description)
+        .replace("{tags}", tags)
+        .replace("{dir}", dir)
+        .replace("{file}", file)
+        .trim_end()
+        .to_string()
+}
+
+/// Builds the picker header describing active tag filters.
+fn tag_filter_header(include_tags: &[String], exclude_tags: &[String]) -> String {
+    let mut parts = Vec::new();
+    if !include_tags.is_empty() {
+        parts.push(format!("tags: {}", plain_tags(include_tags)));
+    }
+    if !exclude_tags.is_empty() {
+        parts.push(format!("excluding: {}", plain_tags(exclude_tags)));
+    }
+    parts.join("  ")
+}
+
+/// Fits the picker header into `width` columns. When the tag list is too
+/// long, whole tags are dropped from the end and replaced with a
+/// `(+N more)` note, so fzf doesn't wrap or clip the header.
+fn fit_header(header: &str, width: usize) -> String {
+    if header.chars().count() <= width {
+        return header.to_string();
+    }
+    let tokens: Vec<&str> = header.split_whitespace().collect();
+    for keep in (1..tokens.len()).rev() {
+        let dropped = tokens[keep..]
+            .iter()
+            .filter(|token| token.starts_with('#'))
+            .count();
+        let candidate = format!("{} (+{dropped} more)", tokens[..keep].join(" "));
+        if candidate.chars().count() <= width {
+            return candidate;
+        }
+    }
+    // Nothing fits; at least say how much is hidden.
+    let total = tokens.iter().filter(|token| token.starts_with('#')).count();
+    format!("(+{total} tags)")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dim_and_highlight_wrap_in_resets() {
+        assert_eq!(dim("x"), "\x1b[2mx\x1b[0m");
+        assert_eq!(highlight("x"), "\x1b[1mx\x1b[0m");
+    }
+
+    #[test]
+    fn tag_colors_are_stable() {
+        assert_eq!(color_tag("git"), color_tag("git"));
+    }
+
+    #[test]
+    fn plain_tags_have_no_escapes() {
+        let rendered = plain_tags(&["git".to_string(), "work".to_string()]);
+        assert_eq!(rendered, "#git #work");
+    }
+
+    fn tagged_def() -> CommandDef {
+        CommandDef {
+            description: "Deploy".to_string(),
+            command: "deploy".to_string(),
+            id: None,
+            tags: vec!["work".to_string()],
+            confirm: Default::default(),
+            env: Default::default(),
+            cwd: None,
+            defaults: Default::default(),
+            log_file: None,
+            login_shell: false,
+            priority: 0,
+            success_codes: vec![0],
+            params: Vec::new(),
+            base: None,
+            expand: Default::default(),
+            retries: 0,
+            retry_delay_secs: 0,
+            alternatives: Vec::new(),
+            enabled: true,
+            icon: None,
+            source_file: std::path::PathBuf::from("/tmp/test.toml"),
+            line: 1,
+        }
+    }
+
+    #[test]
+    fn multi_line_commands_stay_on_one_picker_line() {
+        let mut def = tagged_def();
+        def.description = "Two\nlines".to_string();
+        def.command = "echo one\necho two".to_string();
+        let (plain, display) = render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, true, false, None, false, false);
+        assert!(!plain.contains('\n'));
+        assert!(!display.contains('\n'));
+        // The real command is untouched; only the rendering is collapsed.
+        assert!(def.command.contains('\n'));
+    }
+
+    #[test]
+    fn non_ansi_filters_get_escape_free_lines() {
+        let def = tagged_def();
+        let (plain, display) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, false, None, false, false);
+        assert!(!display.contains('\x1b'));
+        assert_eq!(plain, display);
+    }
+
+    #[test]
+    fn ansi_filters_get_colored_tags() {
+        let def = tagged_def();
+        let (plain, display) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, true, None, false, false);
+        assert!(display.contains('\x1b'));
+        assert!(!plain.contains('\x1b'));
+    }
+
+    #[test]
+    fn default_template_matches_the_classic_layout() {
+        let def = tagged_def();
+        let (plain, _) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, false, None, false, false);
+        assert_eq!(plain, "Deploy #work");
+    }
+
+    #[test]
+    fn truncation_keeps_the_key_column_intact() {
+        let mut def = tagged_def();
+        def.description = "A very long description that overflows".to_string();
+        let (plain, display) =
+            render_line(&def, "{description}", true, false, false, Some(10), false, false);
+        let label = plain.split('\t').next().unwrap();
+        assert_eq!(label.chars().count(), 10);
+        assert!(label.ends_with('…'));
+        assert!(plain.ends_with(&format!("\t{}", def.key())));
+        assert_eq!(plain, display);
+    }
+
+    #[test]
+    fn short_labels_are_not_truncated() {
+        assert_eq!(truncate_label("short", 10), "short");
+        assert_eq!(truncate_label("exactly-10", 10), "exactly-10");
+    }
+
+    #[test]
+    fn label_template_supports_dir_and_file_tokens() {
+        let def = tagged_def();
+        let rendered =
+            apply_label_template("{description} ({dir}/{file})", &def, "");
+        assert_eq!(rendered, "Deploy (tmp/test.toml)");
+    }
+
+    #[test]
+    fn non_auto_filter_commands_pass_through() {
+        assert_eq!(resolve_filter_command("grep x"), "grep x");
+    }
+
+    #[test]
+    fn auto_resolves_to_a_known_candidate_or_the_default() {
+        let resolved = resolve_filter_command("auto");
+        assert!(
+            AUTO_FILTER_CANDIDATES.contains(&resolved.as_str())
+                || resolved == crate::config::DEFAULT_FILTER_COMMAND
+        );
+    }
+
+    #[test]
+    fn query_flags_cover_the_known_filters() {
+        assert_eq!(query_flag("fzf"), Some("--query"));
+        assert_eq!(query_flag("sk"), Some("--query"));
+        assert_eq!(query_flag("peco"), Some("--query"));
+        assert_eq!(query_flag("gum"), Some("--value"));
+        assert_eq!(query_flag("my-custom-filter"), None);
+    }
+
+    #[test]
+    fn command_text_column_resolves_to_the_right_selection() {
+        let mut wanted = tagged_def();
+        wanted.description = "Restart the web thing".to_string();
+        wanted.command = "systemctl restart nginx".to_string();
+        let commands = vec![tagged_def(), wanted];
+        let config = AppConfig {
+            filter_command: "grep nginx".to_string(),
+            search_command_text: true,
+            ..AppConfig::default()
+        };
+        let chosen = choose_command(&commands, &config, None, &[], &[])
+            .unwrap()
+            .expect("grep should match the command text");
+        assert_eq!(chosen.command, "systemctl restart nginx");
+    }
+
+    #[test]
+    fn icons_ride_in_a_display_only_column() {
+        let mut def = tagged_def();
+        def.icon = Some("★".to_string());
+        let (plain, display) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, false, None, true, false);
+        assert_eq!(plain, "★\tDeploy #work");
+        assert_eq!(plain, display);
+        // The match fields (everything after the icon column) are
+        // icon-free, so `--nth=2` keeps the glyph out of fuzzy matching.
+        assert!(!plain.split_once('\t').unwrap().1.contains('★'));
+
+        // Icon-less entries get a placeholder column for alignment.
+        let (plain, _) = render_line(
+            &tagged_def(),
+            crate::config::DEFAULT_LABEL_TEMPLATE,
+            false,
+            false,
+            false,
+            None,
+            true,
+            false,
+        );
+        assert_eq!(plain, " \tDeploy #work");
+    }
+
+    #[test]
+    fn sort_tags_renders_tags_alphabetically() {
+        let mut def = tagged_def();
+        def.tags = vec!["zsh".to_string(), "aws".to_string(), "git".to_string()];
+        let (sorted, _) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, false, None, false, true);
+        assert_eq!(sorted, "Deploy #aws #git #zsh");
+        // Off by default: authored order survives.
+        let (authored, _) =
+            render_line(&def, crate::config::DEFAULT_LABEL_TEMPLATE, false, false, false, None, false, false);
+        assert_eq!(authored, "Deploy #zsh #aws #git");
+    }
+
+    #[test]
+    fn iconed_selections_resolve_to_the_right_command() {
+        let mut iconed = tagged_def();
+        iconed.icon = Some("★".to_string());
+        iconed.description = "Restart nginx".to_string();
+        let commands = vec![tagged_def(), iconed];
+        let config = AppConfig {
+            filter_command: "grep Restart".to_string(),
+            ..AppConfig::default()
+        };
+        let chosen = choose_command(&commands, &config, None, &[], &[])
+            .unwrap()
+            .expect("the icon must not break selection lookup");
+        assert_eq!(chosen.description, "Restart nginx");
+    }
+
+    #[test]
+    fn choose_string_maps_the_selected_line_back() {
+        let options = vec!["echo one".to_string(), "echo two".to_string()];
+        let config = AppConfig {
+            filter_command: "head -n1".to_string(),
+            ..AppConfig::default()
+        };
+        let chosen = choose_string(&options, &config).unwrap();
+        assert_eq!(chosen.as_deref(), Some("echo one"));
+    }
+
+    #[test]
+    fn auto_select_args_apply_only_to_fzf() {
+        assert_eq!(auto_select_args(true, true), ["--select-1", "--exit-0"]);
+        assert!(auto_select_args(true, false).is_empty());
+        assert!(auto_select_args(false, true).is_empty());
+    }
+
+    #[test]
+    fn selection_keys_split_on_the_configured_field() {
+        let spec = crate::config::FilterOutput {
+            delimiter: ":".to_string(),
+            field: 0,
+        };
+        assert_eq!(selection_key("3:Deploy #work", &spec), "3");
+        // Degenerate lines fall through whole, failing the lookup cleanly.
+        assert_eq!(selection_key("no-delimiter", &spec), "no-delimiter");
+    }
+
+    #[test]
+    fn index_prefixed_selections_resolve_to_the_right_command() {
+        let mut wanted = tagged_def();
+        wanted.description = "Restart nginx".to_string();
+        let commands = vec![tagged_def(), wanted];
+        // grep stands in for a filter that matches on the index column.
+        let config = AppConfig {
+            filter_command: "grep ^1:".to_string(),
+            filter_output: Some(crate::config::FilterOutput {
+                delimiter: ":".to_string(),
+                field: 0,
+            }),
+            ..AppConfig::default()
+        };
+        let chosen = choose_command(&commands, &config, None, &[], &[])
+            .unwrap()
+            .expect("the index column should resolve the selection");
+        assert_eq!(chosen.description, "Restart nginx");
+    }
+
+    #[test]
+    fn interrupt_handler_installs_once() {
+        // ctrlc rejects a second handler; the Once must make repeat calls
+        // (one per picker invocation) harmless.
+        install_interrupt_handler();
+        install_interrupt_handler();
+    }
+
+    #[test]
+    fn long_headers_are_cut_at_a_tag_boundary() {
+        let tags: Vec<String> =
+            (0..20).map(|n| format!("tag-number-{n}")).collect();
+        let header = tag_filter_header(&tags, &[]);
+        let fitted = fit_header(&header, 40);
+        assert!(fitted.chars().count() <= 40, "too wide: {fitted:?}");
+        assert!(fitted.starts_with("tags: #tag-number-0"));
+        assert!(fitted.ends_with("more)"));
+    }
+
+    #[test]
+    fn short_headers_are_untouched() {
+        assert_eq!(fit_header("tags: #git", 80), "tags: #git");
+    }
+
+    #[test]
+    fn header_mentions_both_filter_kinds() {
+        let header =
+            tag_filter_header(&["git".to_string()], &["slow".to_string()]);
+        assert!(header.contains("tags: #git"));
+        assert!(header.contains("excluding: #slow"));
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Where cmdy keeps mutable state (usage counts, the remembered query):
+/// `$XDG_STATE_HOME/cmdy` or `~/.local/state/cmdy`.
+fn get_state_dir() -> Option<PathBuf> {
+    if let Ok(xdg) = env::var("XDG_STATE_HOME") {
+        if !xdg.is_empty() {
+            return Some(PathBuf::from(xdg).join("cmdy"));
+        }
+    }
+    let home = env::var("HOME").ok()?;
+    Some(PathBuf::from(home).join(".local").join("state").join("cmdy"))
+}
+
+fn usage_file() -> Option<PathBuf> {
+    Some(get_state_dir()?.join("usage.toml"))
+}
+
+fn query_file() -> Option<PathBuf> {
+    Some(get_state_dir()?.join("last-query"))
+}
+
+/// The query saved by the previous run, when `remember_query` is enabled.
+pub fn load_last_query() -> Option<String> {
+    let contents = fs::read_to_string(query_file()?).ok()?;
+    let query = contents.trim();
+    if query.is_empty() {
+        None
+    } else {
+        Some(query.to_string())
+    }
+}
+
+/// Saves the last query, or clears it with `None`. Best-effort, like the
+/// usage counts: a read-only state dir never blocks a run.
+pub fn save_last_query(query: Option<&str>) {
+    let Some(path) = query_file() else { return };
+    match query {
+        Some(query) => {
+            if let Some(parent) = path.parent() {
+                if fs::create_dir_all(parent).is_err() {
+                    return;
+                }
+            }
+            let _ = fs::write(path, query);
+        }
+        None => {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+fn last_run_file() -> Option<PathBuf> {
+    Some(get_state_dir()?.join("last-run"))
+}
+
+/// The uniqueness key of the most recently executed snippet, if any.
+pub fn load_last_run() -> Option<String> {
+    let contents = fs::read_to_string(last_run_file()?).ok()?;
+    let key = contents.trim();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key.to_string())
+    }
+}
+
+/// Records the snippet that just ran, for `--last`. Best-effort.
+pub fn save_last_run(key: &str) {
+    let Some(path) = last_run_file() else { return };
+    if let Some(parent) = path.parent() {
+        if fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    let _ = fs::write(path, key);
+}
+
+fn history_file() -> Option<PathBuf> {
+    Some(get_state_dir()?.join("history.jsonl"))
+}
+
+/// One executed command in cmdy's own append-only history log, kept
+/// independently of shell history so it survives across shells.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    /// Seconds since the Unix epoch.
+    pub timestamp: u64,
+    pub description: String,
+    pub command: String,
+    /// The exit code, or `None` when the command died to a signal.
+    pub status: Option<i32>,
+}
+
+/// Appends one record to `history.jsonl`. Best-effort, like everything
+/// else in the state dir.
+pub fn append_history(record: &HistoryRecord) {
+    let Some(path) = history_file() else { return };
+    if let Some(parent) = path.parent() {
+        if fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    let Ok(line) = serde_json::to_string(record) else {
+        return;
+    };
+    let _ = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| {
+            use std::io::Write as _;
+            writeln!(file, "{line}")
+        });
+}
+
+/// The most recent `last` history records, oldest first. Unparseable lines
+/// (from older versions, say) are skipped.
+pub fn load_history(last: usize) -> Vec<HistoryRecord> {
+    let Some(path) = history_file() else {
+        return Vec::new();
+    };
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    let records: Vec<HistoryRecord> = contents
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect();
+    let skip = records.len().saturating_sub(last);
+    records.into_iter().skip(skip).collect()
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct UsageFile {
+    #[serde(default)]
+    counts: BTreeMap<String, u64>,
+}
+
+/// Loads per-description usage counts. Missing or unreadable state yields an
+/// empty map; usage tracking is best-effort by design.
+pub fn load_usage() -> BTreeMap<String, u64> {
+    let Some(path) = usage_file() else {
+        return BTreeMap::new();
+    };
+    let Ok(contents) = fs::read_to_string(path) else {
+        return BTreeMap::new();
+    };
+    toml::from_str::<UsageFile>(&contents)
+        .map(|file| file.counts)
+        .unwrap_or_default()
+}
+
+/// Bumps the usage count for a command. Failures are swallowed: losing a
+/// count must never break running the command itself.
+pub fn record_usage(description: &str) {
+    let Some(path) = usage_file() else { return };
+    let mut counts = load_usage();
+    *counts.entry(description.to_string()).or_insert(0) += 1;
+    let mut contents = String::from("[counts]\n");
+    for (description, count) in &counts {
+        contents.push_str(&format!("{description:?} = {count}\n"));
+    }
+    if let Some(parent) = path.parent() {
+        if fs::create_dir_all(parent).is_err() {
+            return;
+        }
+    }
+    let _ = fs::write(path, contents);
+}